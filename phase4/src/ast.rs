@@ -0,0 +1,199 @@
+use crate::diagnostic::Span;
+
+// The abstract syntax tree produced by the parser.
+// The parser only checks the syntax of the program. Semantic checks and
+// IR generation are done afterwards by walking this tree (see codegen.rs).
+// Every node remembers the span of the token it starts at, for error messages.
+// Every node that names a variable also has an ir_name, which the parser
+// leaves empty. The type checker resolves the variable against the scopes of
+// its function and fills it in with the name used in the IR (see typecheck.rs).
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+// func name(int a, int b) { ... }
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ir_name: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    // int a;
+    // int a = b + c;
+    // int [8] a;
+    // int a[8];
+    Declaration {
+        name: String,
+        ir_name: Option<String>,
+        size: Option<i32>,
+        value: Option<Expr>,
+    },
+
+    // a = b + c;
+    // a[i] = b + c;
+    Assignment {
+        name: String,
+        ir_name: Option<String>,
+        index: Option<Expr>,
+        value: Expr,
+    },
+
+    // a += b;
+    // a[i] *= b;
+    // a++; is stored as a += 1;
+    // the index of an array element is only evaluated once.
+    CompoundAssignment {
+        name: String,
+        ir_name: Option<String>,
+        index: Option<Expr>,
+        op: BinaryOp,
+        value: Expr,
+    },
+
+    // f(a, b);
+    // the return value of the function is discarded.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+
+    Return(Expr),
+    Print(Expr),
+    Read(Expr),
+    Break,
+    Continue,
+
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+    },
+
+    // for (int i = 0; i < n; i = i + 1) { ... }
+    // init is a declaration or an assignment, and step is an assignment.
+    For {
+        init: Box<Stmt>,
+        condition: Expr,
+        step: Box<Stmt>,
+        body: Vec<Stmt>,
+    },
+
+    // do { ... } while i < n;
+    DoWhile {
+        body: Vec<Stmt>,
+        condition: Expr,
+    },
+
+    // else if ... is an If statement alone in else_body.
+    If {
+        condition: Expr,
+        body: Vec<Stmt>,
+        else_body: Vec<Stmt>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Number(i32),
+    Ident {
+        name: String,
+        ir_name: Option<String>,
+    },
+
+    // a[i]
+    Index {
+        name: String,
+        ir_name: Option<String>,
+        index: Box<Expr>,
+    },
+
+    // f(a, b)
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+
+    // -a
+    // ~a
+    // !(a < b)
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+
+    // a < b && c < d
+    // the right operand is only evaluated when it decides the result.
+    Logical {
+        op: LogicalOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    BitNot,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    // mathematical operators.
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+
+    // bitwise operators.
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+
+    // comparison operators.
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equality,
+    NotEqual,
+}
@@ -0,0 +1,918 @@
+use crate::ast::{BinaryOp, Expr, ExprKind, Function, LogicalOp, Program, Stmt, StmtKind, UnaryOp};
+use crate::diagnostic::{Diagnostic, Span};
+
+// Lowers the syntax tree into the IR understood by the interpreter.
+// The variables, their types and the function calls have already been
+// checked by the type checker (see typecheck.rs), which also filled in the
+// ir_name of every variable. The remaining semantic checks (a missing
+// main function, break/continue outside of a loop) are done here.
+
+struct Expression {
+  code: String,
+  name: String,
+}
+
+// Helper function to check if the program has a main function
+fn has_main(program: &Program) -> bool {
+    for function in &program.functions {
+        if function.name.eq("main") {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Helper function to get the name in the IR of a variable resolved by the type checker.
+fn ir_name(name: &String, ir_name: &Option<String>, span: Span) -> Result<String, Diagnostic> {
+    match ir_name {
+    Some(ir_name) => Ok(ir_name.clone()),
+    None => Err(Diagnostic::new(span, format!("Variable {name} has not been resolved by the type checker"))),
+    }
+}
+
+// All of the state used while lowering one program: the counters used to
+// create unique temporaries and labels, and the loops being lowered.
+// Every compilation gets a fresh context, so compiling the same program
+// always generates the same IR.
+pub struct CodegenContext {
+    var_num: i64,
+    if_num: i64,
+    loop_num: i64,
+    logic_num: i64,
+    // Loops enclosing the current statement, innermost last.
+    // break and continue jump to the labels of the innermost loop.
+    loops: Vec<LoopLabels>,
+}
+
+// The labels that break and continue jump to inside a loop.
+// break always leaves the loop, while continue goes to the part of the loop
+// that comes after the body: the condition of a while loop or a do while
+// loop, and the step of a for loop.
+struct LoopLabels {
+    break_label: String,
+    continue_label: String,
+}
+
+impl CodegenContext {
+    pub fn new() -> CodegenContext {
+        CodegenContext {
+            var_num: 0,
+            if_num: 0,
+            loop_num: 0,
+            logic_num: 0,
+            loops: vec![],
+        }
+    }
+
+    fn create_temp(&mut self) -> String {
+        self.var_num += 1;
+        format!("_temp{}", self.var_num)
+    }
+
+    fn create_if(&mut self) -> String {
+        self.if_num += 1;
+        format!("_if{}", self.if_num)
+    }
+
+    fn create_loop(&mut self) -> String {
+        self.loop_num += 1;
+        format!("_loop{}", self.loop_num)
+    }
+
+    fn create_logic(&mut self) -> String {
+        self.logic_num += 1;
+        format!("_logic{}", self.logic_num)
+    }
+}
+
+// lower programs with multiple functions
+// loop over everything, outputting generated code.
+pub fn lower_program(program: &Program) -> Result<String, Diagnostic> {
+    let mut ctx = CodegenContext::new();
+    let mut code = String::new();
+    for function in &program.functions {
+        code += &lower_function(function, &mut ctx)?;
+    }
+    // After the program is done
+    // Check if we have the main function
+    if !has_main(program){
+      return Err(Diagnostic::without_span(String::from("Main function not detected in the program")));
+    }
+    return Ok(code);
+}
+
+fn lower_function(function: &Function, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let ident = &function.name;
+    let mut function_code = format!("%func {ident}");
+
+    let mut params: Vec<String> = vec![];
+    for param in &function.params {
+        params.push(format!("%int {}", ir_name(&param.name, &param.ir_name, param.span)?));
+    }
+    if !params.is_empty() {
+        function_code += &format!("({})", params.join(", "));
+    }
+    function_code += "\n";
+
+    for statement in &function.body {
+        // Each statement should contain a newline itself
+        function_code += &lower_statement(statement, ctx)?;
+    }
+
+    function_code += "%endfunc\n";
+    return Ok(function_code);
+}
+
+fn lower_block(statements: &Vec<Stmt>, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let mut code = String::new();
+    for statement in statements {
+        code += &lower_statement(statement, ctx)?;
+    }
+    return Ok(code);
+}
+
+// Each statement starts with a '%line' mapping to its line in the source code,
+// so that runtime errors in the interpreter can point back to it.
+fn lower_statement(statement: &Stmt, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let code = lower_statement_code(statement, ctx)?;
+    return Ok(source_line(statement.span) + &code);
+}
+
+// Conditions evaluated after the body of a loop get their own '%line' mapping.
+// Lines start at 1, so a span without a line (in a tree built by hand) has no mapping.
+fn source_line(span: Span) -> String {
+    if span.line == 0 {
+        return String::new();
+    }
+    return format!("%line {}\n", span.line);
+}
+
+fn lower_statement_code(statement: &Stmt, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let span = statement.span;
+    match &statement.kind {
+    StmtKind::Declaration { name, ir_name: resolved, size, value } => {
+        lower_declaration(ir_name(name, resolved, span)?, size, value, ctx)
+    }
+    StmtKind::Assignment { name, ir_name: resolved, index, value } => {
+        lower_assignment(ir_name(name, resolved, span)?, index, value, ctx)
+    }
+    StmtKind::CompoundAssignment { name, ir_name: resolved, index, op, value } => {
+        lower_compound_assignment(ir_name(name, resolved, span)?, index, *op, value, ctx)
+    }
+
+    // The return value is not stored anywhere
+    StmtKind::Call { name, args } => {
+        let (mut statement, call) = lower_call(name, args, ctx)?;
+        statement += &format!("%call {call}\n");
+        Ok(statement)
+    }
+
+    StmtKind::Return(expr) => {
+        let expression = lower_expression(expr, ctx)?;
+        let mut statement = expression.code;
+        statement += &format!("%ret {}\n", expression.name);
+        Ok(statement)
+    }
+
+    StmtKind::Print(expr) => {
+        let expression = lower_expression(expr, ctx)?;
+        let mut statement = expression.code;
+        let src1 = expression.name;
+        let dest = ctx.create_temp();
+        statement += &format!("%int {dest}\n");
+        statement += &format!("%mov {}, {}\n", dest, src1);
+        statement += &format!("%out {dest}\n");
+        Ok(statement)
+    }
+
+    StmtKind::Read(expr) => lower_read(expr, ctx),
+
+    StmtKind::Break => {
+        // Check if we are inside a loop
+        match ctx.loops.last() {
+        // We will be jumping to the end of the innermost loop
+        Some(labels) => Ok(format!("%jmp :{}\n", labels.break_label)),
+        None => Err(Diagnostic::new(span, String::from("Break statement not inside a loop"))),
+        }
+    }
+
+    StmtKind::Continue => {
+        // Check if we are inside a loop
+        match ctx.loops.last() {
+        // We will be jumping to the next iteration of the innermost loop
+        Some(labels) => Ok(format!("%jmp :{}\n", labels.continue_label)),
+        None => Err(Diagnostic::new(span, String::from("Continue statement not inside a loop"))),
+        }
+    }
+
+    StmtKind::While { condition, body } => lower_while_loop(condition, body, ctx),
+    StmtKind::For { init, condition, step, body } => lower_for_loop(init, condition, step, body, ctx),
+    StmtKind::DoWhile { body, condition } => lower_do_while_loop(body, condition, ctx),
+    StmtKind::If { condition, body, else_body } => lower_if_statement(condition, body, else_body, ctx),
+    }
+}
+
+fn lower_declaration(ir_name: String, size: &Option<i32>, value: &Option<Expr>, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    match size {
+    None => {
+        let mut expression: Option<Expression> = None;
+        if let Some(value) = value {
+            expression = Some(lower_expression(value, ctx)?);
+        }
+        let mut statement = format!("%int {ir_name}\n");
+        if let Some(expression) = expression {
+            statement += &lower_store(&ir_name, expression);
+        }
+        Ok(statement)
+    }
+    Some(num) => {
+        Ok(format!("%int[] {ir_name}, {num}\n"))
+    }
+    }
+}
+
+fn lower_assignment(ir_name: String, index: &Option<Expr>, value: &Expr, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let mut statement = String::new();
+
+    // Get the dest (left part)
+    let dest: String;
+    match index {
+    Some(index) => {
+        let expression = lower_expression(index, ctx)?;
+        statement += &expression.code;
+        dest = format!("[{} + {}]", ir_name, expression.name);
+    }
+    None => {
+        dest = ir_name;
+    }
+    }
+
+    let expression = lower_expression(value, ctx)?;
+    statement += &lower_store(&dest, expression);
+    return Ok(statement);
+}
+
+// %input can only store into an integer, so read(a[i]) is lowered as
+// %input temp
+// %mov [a + i], temp
+// The type checker has made sure that expr is a variable or an array element.
+fn lower_read(expr: &Expr, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    match &expr.kind {
+    ExprKind::Index { name, ir_name: resolved, index } => {
+        let array = ir_name(name, resolved, expr.span)?;
+        let expression = lower_expression(index, ctx)?;
+        let temp = ctx.create_temp();
+        let mut statement = expression.code;
+        statement += &format!("%int {temp}\n");
+        statement += &format!("%input {temp}\n");
+        statement += &format!("%mov [{} + {}], {}\n", array, expression.name, temp);
+        Ok(statement)
+    }
+
+    _ => {
+        let expression = lower_expression(expr, ctx)?;
+        let mut statement = expression.code;
+        statement += &format!("%input {}\n", expression.name);
+        Ok(statement)
+    }
+    }
+}
+
+// a[i] += b is lowered as
+// %mov old, [a + i]
+// %add new, old, b
+// %mov [a + i], new
+// so that i is only evaluated once. a += b on an integer is just %add a, a, b.
+fn lower_compound_assignment(ir_name: String, index: &Option<Expr>, op: BinaryOp, value: &Expr, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let opcode = binary_opcode(op);
+    match index {
+    Some(index) => {
+        let index_expression = lower_expression(index, ctx)?;
+        let mut statement = index_expression.code;
+        let dest = format!("[{} + {}]", ir_name, index_expression.name);
+        let old = ctx.create_temp();
+        statement += &format!("%int {old}\n");
+        statement += &format!("%mov {old}, {dest}\n");
+
+        let expression = lower_expression(value, ctx)?;
+        statement += &expression.code;
+        let new = ctx.create_temp();
+        statement += &format!("%int {new}\n");
+        statement += &format!("{opcode} {new}, {old}, {}\n", expression.name);
+        statement += &format!("%mov {dest}, {new}\n");
+        Ok(statement)
+    }
+    None => {
+        let expression = lower_expression(value, ctx)?;
+        let mut statement = expression.code;
+        statement += &format!("{opcode} {ir_name}, {ir_name}, {}\n", expression.name);
+        Ok(statement)
+    }
+    }
+}
+
+// Stores the result of the lowered expression into dest
+fn lower_store(dest: &String, expression: Expression) -> String {
+    let mut statement = expression.code;
+    statement += &format!("%mov {dest}, {}\n", expression.name);
+    return statement;
+}
+
+fn lower_if_statement(condition: &Expr, body: &Vec<Stmt>, else_body: &Vec<Stmt>, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+  // Get current if num
+  let curr_if = ctx.create_if();
+
+  let boolean_expression = lower_expression(condition, ctx)?;
+  let if_statement_body = lower_block(body, ctx)?;
+  let else_body_statement = lower_block(else_body, ctx)?;
+
+  let mut if_statement = String::from("");
+  if_statement += &boolean_expression.code;
+  if_statement += &format!("%branch_if {}, :{curr_if}true\n", boolean_expression.name);
+  if_statement += &format!("%jmp :{curr_if}_else\n");
+  if_statement += &format!(":{curr_if}true\n");
+  if_statement += &if_statement_body;
+  if_statement += &format!("%jmp :end{curr_if}\n");
+  if_statement += &format!(":{curr_if}_else\n");
+  if_statement += &else_body_statement;
+  if_statement += &format!(":end{curr_if}\n");
+
+  return Ok(if_statement);
+}
+
+fn lower_while_loop(condition: &Expr, body: &Vec<Stmt>, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    // Get current loop num
+    let curr_loop = ctx.create_loop();
+
+    let boolean_expression = lower_expression(condition, ctx)?;
+
+    // break and continue inside the body refer to this loop
+    let labels = LoopLabels { break_label: format!("end{curr_loop}"), continue_label: format!("{curr_loop}_begin") };
+    let while_loop_body = lower_loop_body(body, labels, ctx)?;
+
+    let mut loop_code = String::from("");
+    // Thus different loop have its own num, and now capable with nested loop
+    loop_code += &format!(":{curr_loop}_begin\n");
+    loop_code += &boolean_expression.code;
+    loop_code += &format!("%branch_ifn {}, :end{}\n", boolean_expression.name, curr_loop);
+    loop_code += &while_loop_body;
+    loop_code += &format!("%jmp :{curr_loop}_begin\n");
+    loop_code += &format!(":end{curr_loop}\n");
+
+    return Ok(loop_code);
+}
+
+// for (init; condition; step) { body } is lowered as
+// init
+// :_loopN_begin
+// if !condition goto :end_loopN
+// body
+// :_loopN_continue
+// step
+// goto :_loopN_begin
+// :end_loopN
+fn lower_for_loop(init: &Stmt, condition: &Expr, step: &Stmt, body: &Vec<Stmt>, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let curr_loop = ctx.create_loop();
+
+    let init_code = lower_statement(init, ctx)?;
+    let boolean_expression = lower_expression(condition, ctx)?;
+    let labels = LoopLabels { break_label: format!("end{curr_loop}"), continue_label: format!("{curr_loop}_continue") };
+    let for_loop_body = lower_loop_body(body, labels, ctx)?;
+    let step_code = lower_statement(step, ctx)?;
+
+    let mut loop_code = init_code;
+    loop_code += &format!(":{curr_loop}_begin\n");
+    loop_code += &source_line(condition.span);
+    loop_code += &boolean_expression.code;
+    loop_code += &format!("%branch_ifn {}, :end{}\n", boolean_expression.name, curr_loop);
+    loop_code += &for_loop_body;
+    loop_code += &format!(":{curr_loop}_continue\n");
+    loop_code += &step_code;
+    loop_code += &format!("%jmp :{curr_loop}_begin\n");
+    loop_code += &format!(":end{curr_loop}\n");
+
+    return Ok(loop_code);
+}
+
+// do { body } while condition; is lowered as
+// :_loopN_begin
+// body
+// :_loopN_continue
+// if condition goto :_loopN_begin
+// :end_loopN
+fn lower_do_while_loop(body: &Vec<Stmt>, condition: &Expr, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    let curr_loop = ctx.create_loop();
+
+    let labels = LoopLabels { break_label: format!("end{curr_loop}"), continue_label: format!("{curr_loop}_continue") };
+    let do_loop_body = lower_loop_body(body, labels, ctx)?;
+    let boolean_expression = lower_expression(condition, ctx)?;
+
+    let mut loop_code = String::from("");
+    loop_code += &format!(":{curr_loop}_begin\n");
+    loop_code += &do_loop_body;
+    loop_code += &format!(":{curr_loop}_continue\n");
+    loop_code += &source_line(condition.span);
+    loop_code += &boolean_expression.code;
+    loop_code += &format!("%branch_if {}, :{}_begin\n", boolean_expression.name, curr_loop);
+    loop_code += &format!(":end{curr_loop}\n");
+
+    return Ok(loop_code);
+}
+
+// Lowers the body of a loop, where break and continue jump to the given labels
+fn lower_loop_body(body: &Vec<Stmt>, labels: LoopLabels, ctx: &mut CodegenContext) -> Result<String, Diagnostic> {
+    ctx.loops.push(labels);
+    let code = lower_block(body, ctx);
+    ctx.loops.pop();
+    return code;
+}
+
+fn binary_opcode(op: BinaryOp) -> &'static str {
+    match op {
+    BinaryOp::Add => "%add",
+    BinaryOp::Subtract => "%sub",
+    BinaryOp::Multiply => "%mult",
+    BinaryOp::Divide => "%div",
+    BinaryOp::Modulus => "%mod",
+    BinaryOp::BitAnd => "%and",
+    BinaryOp::BitOr => "%or",
+    BinaryOp::BitXor => "%xor",
+    BinaryOp::ShiftLeft => "%shl",
+    BinaryOp::ShiftRight => "%shr",
+    BinaryOp::Less => "%lt",
+    BinaryOp::LessEqual => "%le",
+    BinaryOp::Greater => "%gt",
+    BinaryOp::GreaterEqual => "%ge",
+    BinaryOp::Equality => "%eq",
+    BinaryOp::NotEqual => "%neq",
+    }
+}
+
+// Lowers the arguments of a call.
+// Returns their code, and the call itself in the form "f(a,b)"
+fn lower_call(name: &String, args: &Vec<Expr>, ctx: &mut CodegenContext) -> Result<(String, String), Diagnostic> {
+    let mut code = String::new();
+    let mut arg_names = Vec::new();
+    for arg in args {
+        let inner_expr = lower_expression(arg, ctx)?;
+        code += &inner_expr.code;
+        arg_names.push(inner_expr.name);
+    }
+    return Ok((code, format!("{}({})", name, arg_names.join(","))));
+}
+
+fn lower_expression(expr: &Expr, ctx: &mut CodegenContext) -> Result<Expression, Diagnostic> {
+    match &expr.kind {
+
+    // If just a number, return immediately
+    ExprKind::Number(number) => {
+        Ok(Expression {
+            code: String::from(""),
+            name: number.to_string(),
+        })
+    }
+
+    // Identifier itself is a valid term
+    ExprKind::Ident { name, ir_name: resolved } => {
+        Ok(Expression {
+            code: String::from(""),
+            name: ir_name(name, resolved, expr.span)?,
+        })
+    }
+
+    // Using temp here to avoid %add _temp4, [array + 0], [array + 0]
+    ExprKind::Index { name, ir_name: resolved, index } => {
+        let array = ir_name(name, resolved, expr.span)?;
+        let inner_expr = lower_expression(index, ctx)?;
+        let src1 = format!("[{} + {}]", array, inner_expr.name);
+        let dest = ctx.create_temp();
+        let mut code = inner_expr.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("%mov {}, {}\n", dest, src1);
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    // Identifier (Expression(, Expression)*)
+    // The result of the call is stored in a temp, so calls can be used inside any expression
+    ExprKind::Call { name, args } => {
+        let (mut code, call) = lower_call(name, args, ctx)?;
+        let dest = ctx.create_temp();
+        code += &format!("%int {dest}\n");
+        code += &format!("%call {dest}, {call}\n");
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    // -a is lowered as 0 - a, since the IR has no negation instruction
+    ExprKind::Unary { op: UnaryOp::Negate, operand } => {
+        let expr1 = lower_expression(operand, ctx)?;
+        let dest = ctx.create_temp();
+        let mut code = expr1.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("%sub {dest}, 0, {}\n", expr1.name);
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    ExprKind::Unary { op: UnaryOp::BitNot, operand } => {
+        let expr1 = lower_expression(operand, ctx)?;
+        let dest = ctx.create_temp();
+        let mut code = expr1.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("%not {dest}, {}\n", expr1.name);
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    // !a is 1 when a is 0, and 0 when a is 1
+    ExprKind::Unary { op: UnaryOp::Not, operand } => {
+        let expr1 = lower_expression(operand, ctx)?;
+        let dest = ctx.create_temp();
+        let mut code = expr1.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("%eq {dest}, {}, 0\n", expr1.name);
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    // The result starts as the value of the left operand. If that already
+    // decides the result (0 for '&&', 1 for '||'), the right operand is skipped.
+    ExprKind::Logical { op, lhs, rhs } => {
+        let curr_logic = ctx.create_logic();
+        let expr1 = lower_expression(lhs, ctx)?;
+        let expr2 = lower_expression(rhs, ctx)?;
+        let branch = match op {
+        LogicalOp::And => "%branch_ifn",
+        LogicalOp::Or => "%branch_if",
+        };
+        let dest = ctx.create_temp();
+        let mut code = expr1.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("%mov {}, {}\n", dest, expr1.name);
+        code += &format!("{branch} {dest}, :end{curr_logic}\n");
+        code += &expr2.code;
+        code += &format!("%mov {}, {}\n", dest, expr2.name);
+        code += &format!(":end{curr_logic}\n");
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    ExprKind::Binary { op, lhs, rhs } => {
+        let expr1 = lower_expression(lhs, ctx)?;
+        let expr2 = lower_expression(rhs, ctx)?;
+        let opcode = binary_opcode(*op);
+        let src1 = expr1.name;
+        let src2 = expr2.name;
+        let dest = ctx.create_temp();
+        let mut code = expr1.code;
+        code += &expr2.code;
+        code += &format!("%int {dest}\n");
+        code += &format!("{opcode} {dest}, {src1}, {src2}\n");
+        Ok(Expression {
+            code: code,
+            name: dest,
+        })
+    }
+
+    }
+}
+
+#[cfg(test)]
+mod codegen_tests {
+    use crate::ast::{Expr, ExprKind, Function, Program, Stmt, StmtKind};
+    use crate::codegen::lower_program;
+    use crate::diagnostic::{Diagnostic, Span};
+    use crate::lexer::lex;
+    use crate::parser::parse_program;
+    use crate::RunOptions;
+
+    fn compile(code: &str) -> Result<String, Diagnostic> {
+        let tokens = lex(code).unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        crate::compile_to_ir(&program)
+    }
+
+    #[test]
+    fn named_arrays() {
+        let ir = compile("func main() { int [4] a; int [2] b; a[1] = 5; b[0] = a[1]; }").unwrap();
+        assert!(ir.contains("%int[] a, 4\n"));
+        assert!(ir.contains("%int[] b, 2\n"));
+        assert!(ir.contains("%mov [a + 1], 5\n"));
+        assert!(ir.contains("%mov [b + 0], "));
+
+        let error = compile("func main() { int a; a[0] = 1; }").unwrap_err();
+        assert!(error.message == "Integer a cannot be indexed like an array");
+        let error = compile("func main() { int a; int [2] b; a = b; }").unwrap_err();
+        assert!(error.message == "Array b cannot be used as an integer");
+        let error = compile("func main() { int [2] b; b = 1; }").unwrap_err();
+        assert!(error.message == "Array b cannot be used as an integer");
+    }
+
+    #[test]
+    fn declaration_initializers() {
+        let ir = compile("func main() { int[4] a; int b[2]; int i = 0; int j = i + 1; }").unwrap();
+        assert!(ir.contains("%int[] a, 4\n"));
+        assert!(ir.contains("%int[] b, 2\n"));
+        assert!(ir.contains("%int i\n%mov i, 0\n"));
+        assert!(ir.contains("%int j\n%int _temp"));
+        assert!(ir.contains("%mov j, _temp"));
+
+        let error = compile("func main() { int i = i + 1; }").unwrap_err();
+        assert!(error.message == "Variable i called before declaration");
+
+        let tokens = lex("func main() { int[4] a[4]; int b[2] = 1; int[4][8] c; }").unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        assert!(errors[0].message == "array size is given twice");
+        assert!(errors[1].message == "arrays cannot be initialized in their declaration");
+        assert!(errors[2].message == "array size is given twice");
+        assert!(errors[2].span.unwrap().column == 48);
+    }
+
+    #[test]
+    fn block_scopes() {
+        // j is declared in two sibling loops, and i is shadowed inside the if
+        let ir = compile("func main() { int i = 0; while i < 2 { int j = i; i = i + 1; } while i < 4 { int j = i; if j > 0 { int i = 7; print(i); } i = i + 1; } }").unwrap();
+        assert!(ir.contains("%int j\n"));
+        assert!(ir.contains("%int _j_1\n%mov _j_1, i\n"));
+        assert!(ir.contains("%int _i_1\n%mov _i_1, 7\n"));
+        assert!(ir.contains("%mov _temp"));
+
+        // declarations do not leak out of their block
+        let error = compile("func main() { int i = 0; while i < 2 { int j = i; i = i + 1; } print(j); }").unwrap_err();
+        assert!(error.message == "Variable j called before declaration");
+
+        // but cannot be repeated in the same scope, including the parameters
+        let error = compile("func main() { int i; if i < 1 { int k; int k; } }").unwrap_err();
+        assert!(error.message == "Found duplicating declaration inside statement k");
+        let error = compile("func f(int a) { int a; } func main() { }").unwrap_err();
+        assert!(error.message == "Found duplicating declaration inside statement a");
+    }
+
+    #[test]
+    fn reproducible_output() {
+        let code = "func main() { int i = 0; while i < 3 { if i == 1 { print(i); } i = i + 1; } }";
+        let first = compile(code).unwrap();
+        let second = compile(code).unwrap();
+        assert!(first == second);
+        assert!(first.contains("%int _temp1\n"));
+        assert!(first.contains(":_if1true\n"));
+    }
+
+    #[test]
+    fn built_syntax_trees() {
+        // a tree built by hand has no real spans, but its variables are still told apart
+        let variable = |name: &str| Expr { kind: ExprKind::Ident { name: String::from(name), ir_name: None }, span: Span::default() };
+        let declaration = |name: &str, value: i32| Stmt {
+            kind: StmtKind::Declaration {
+                name: String::from(name),
+                ir_name: None,
+                size: None,
+                value: Some(Expr { kind: ExprKind::Number(value), span: Span::default() }),
+            },
+            span: Span::default(),
+        };
+        let body = vec![declaration("a", 1), declaration("b", 2), Stmt { kind: StmtKind::Print(variable("a")), span: Span::default() }];
+        let program = Program { functions: vec![Function { name: String::from("main"), params: vec![], body: body, span: Span::default() }] };
+        let ir = crate::compile_to_ir(&program).unwrap();
+        assert!(ir.contains("%int a\n%mov a, 1\n"));
+        assert!(ir.contains("%int b\n%mov b, 2\n"));
+        assert!(ir.contains("%mov _temp1, a\n"));
+        assert!(!ir.contains("%line"));
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok() && output == vec!["1"]);
+
+        // and the code generator reports variables that the type checker did not resolve
+        let error = lower_program(&program).unwrap_err();
+        assert!(error.message == "Variable a has not been resolved by the type checker");
+    }
+
+    // Returns the position of the given line in the IR
+    fn position(ir: &str, line: &str) -> usize {
+        ir.find(&format!("\n{line}\n")).unwrap()
+    }
+
+    #[test]
+    fn nested_loop_labels() {
+        let ir = compile(include_str!("examples/nested_loop.tt")).unwrap();
+        assert!(position(&ir, ":_loop1_begin") < position(&ir, ":_loop2_begin"));
+        assert!(position(&ir, "%jmp :_loop2_begin") < position(&ir, ":end_loop2"));
+        assert!(position(&ir, ":end_loop2") < position(&ir, "%jmp :_loop1_begin"));
+        assert!(position(&ir, "%jmp :_loop1_begin") < position(&ir, ":end_loop1"));
+
+        let ir = compile(include_str!("examples/break.tt")).unwrap();
+        assert!(position(&ir, "%jmp :end_loop1") < position(&ir, ":end_loop1"));
+    }
+
+    #[test]
+    fn break_continue_targets() {
+        // break and continue inside ifs jump to the innermost loop,
+        // and a break after an inner loop jumps out of the outer loop.
+        let ir = compile("func main() {
+            int i = 0;
+            while i < 3 {
+                int j = 0;
+                while j < 3 {
+                    if j == 1 {
+                        if i == 1 {
+                            break;
+                        }
+                        j = j + 1;
+                        continue;
+                    }
+                    j = j + 1;
+                }
+                if i == 2 {
+                    while i < 10 {
+                        if i > 5 {
+                            break;
+                        }
+                        i = i + 1;
+                    }
+                    break;
+                }
+                i = i + 1;
+            }
+        }").unwrap();
+        let inner_end = position(&ir, ":end_loop2");
+        let innermost_end = position(&ir, ":end_loop3");
+        assert!(position(&ir, "%jmp :end_loop2") < inner_end);
+        assert!(position(&ir, "%jmp :_loop2_begin") < inner_end);
+        assert!(position(&ir, "%jmp :end_loop3") < innermost_end);
+        assert!(position(&ir, "%jmp :end_loop1") > innermost_end);
+
+        let error = compile(include_str!("examples/error1.tt")).unwrap_err();
+        assert!(error.message == "Break statement not inside a loop");
+        let error = compile("func main() { int i; if i < 1 { continue; } }").unwrap_err();
+        assert!(error.message == "Continue statement not inside a loop");
+    }
+
+    #[test]
+    fn calls_inside_expressions() {
+        let ir = compile("func f(int a) { return a + 1; } func main() { int [2] a; int x = f(1) + 2; print(f(x)); if f(1) < 3 { a[f(0)] = f(f(0)); } }").unwrap();
+        assert!(ir.contains("%int _temp2\n%call _temp2, f(1)\n%int _temp3\n%add _temp3, _temp2, 2\n"));
+        assert!(!ir.contains("%add _temp3, f("));
+        assert!(ir.contains("%call _temp4, f(x)\n"));
+        assert!(ir.contains("%call _temp10, f(_temp9)\n%mov [a + _temp8], _temp10\n"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["5"]);
+    }
+
+    #[test]
+    fn call_statements() {
+        let ir = compile("func log_value(int a) { print(a); return 0; } func main() { int x = 3; log_value(x); log_value(x * 2); }").unwrap();
+        assert!(ir.contains("%call log_value(x)\n"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["3", "6"]);
+
+        let error = compile("func main() { int x = 3; log_value(x); }").unwrap_err();
+        assert!(error.message == "Function log_value is not defined");
+
+        // functions without parameters are called with an empty argument list
+        let ir = compile("func tick() { print(7); return 1; } func main() { tick(); print(tick() + tick()); }").unwrap();
+        assert!(ir.contains("%call tick()\n"));
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["7", "7", "7", "2"]);
+        let error = compile("func tick() { return 1; } func main() { tick(1); }").unwrap_err();
+        assert!(error.message == "Function tick expects 0 argument(s), but 1 were given");
+    }
+
+    #[test]
+    fn short_circuit() {
+        // check prints its argument, so it shows which right operands were evaluated
+        let ir = compile("func check(int a) { print(a); return 1; }
+            func main() {
+                int a = 1;
+                int b = 2;
+                if a < b && check(1) == 1 { print(10); }
+                if a > b && check(2) == 1 { print(20); }
+                if a < b || check(3) == 1 { print(30); }
+                if a > b || check(4) == 1 { print(40); }
+                if !(a > b) && !(check(5) == 0) { print(50); }
+            }").unwrap();
+        assert!(ir.contains("%branch_ifn _temp"));
+        assert!(ir.contains(":end_logic1\n"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["1", "10", "30", "4", "40", "5", "50"]);
+    }
+
+    #[test]
+    fn for_and_do_while_loops() {
+        let code = "func main() {
+            int s = 0;
+            for (int i = 0; i < 10; i = i + 1) {
+                if i % 2 == 0 {
+                    continue;
+                }
+                if i > 7 {
+                    break;
+                }
+                s = s + i;
+            }
+            print(s);
+            int j = 0;
+            do {
+                j = j + 1;
+                if j == 2 {
+                    continue;
+                }
+                print(j);
+            } while j < 4;
+        }";
+        let ir = compile(code).unwrap();
+        // continue runs the step of the for loop, and the condition of the do while loop
+        assert!(position(&ir, "%jmp :_loop1_continue") < position(&ir, ":_loop1_continue"));
+        assert!(ir.contains(":_loop1_continue\n%line 3\n%int _temp"));
+        assert!(position(&ir, "%jmp :_loop2_continue") < position(&ir, ":_loop2_continue"));
+        assert!(ir.contains("%branch_if _temp"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["16", "1", "3", "4"]);
+
+        // the variable declared by the for loop does not outlive it
+        let error = compile("func main() { for (int i = 0; i < 3; i = i + 1) { print(i); } print(i); }").unwrap_err();
+        assert!(error.message == "Variable i called before declaration");
+    }
+
+    #[test]
+    fn compound_assignments() {
+        // idx prints its argument, so it shows how many times the index is evaluated
+        let ir = compile("func idx(int i) { print(i); return i; }
+            func main() {
+                int [3] a;
+                int x = 10;
+                x += 5;
+                x -= 1;
+                x *= 3;
+                x /= 2;
+                x %= 8;
+                x++;
+                x--;
+                x++;
+                print(x);
+                a[idx(1)] = 4;
+                a[idx(1)] += 7;
+                a[2]--;
+                print(a[1]);
+                print(a[2]);
+                for (int i = 0; i < 2; i++) { print(i); }
+            }").unwrap();
+        assert!(ir.contains("%add x, x, 5\n"));
+        assert!(ir.contains("%mod x, x, 8\n"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["6", "1", "1", "11", "-1", "0", "1"]);
+
+        let error = compile("func main() { int [2] a; a += 1; }").unwrap_err();
+        assert!(error.message == "Array a cannot be used as an integer");
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        let ir = compile("func main() {
+                int a = 12;
+                int b = 10;
+                print(a & b);
+                print(a | b);
+                print(a ^ b);
+                print(~a);
+                print(1 << 4 + 1);
+                print(-64 >> 2);
+                print(a | b & 3 ^ 1);
+            }").unwrap();
+        assert!(ir.contains("%not _temp"));
+        assert!(ir.contains("%shl _temp"));
+
+        let (result, output) = crate::execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok());
+        assert!(output == vec!["8", "14", "6", "-13", "32", "-16", "15"]);
+
+        // shifting by 32 bits or more, or by a negative amount, is a runtime error
+        for amount in [32, -1] {
+            let ir = compile(&format!("func main() {{ int s = {amount}; print(1 >> s); }}")).unwrap();
+            let error = crate::execute_ir(&ir, vec![], &RunOptions::default()).0.unwrap_err();
+            assert!(error.message.contains("Shift amount"));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+// Location of a token (or the syntax tree node built from it) in the source file.
+// line and column both start counting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+// An error reported by the lexer, the parser or the semantic checks.
+// Diagnostics without a span (such as a missing main function) refer to the whole file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: String) -> Diagnostic {
+        Diagnostic { span: Some(span), message: message }
+    }
+
+    pub fn without_span(message: String) -> Diagnostic {
+        Diagnostic { span: None, message: message }
+    }
+
+    // Formats the diagnostic as:
+    //   file.tt:3:9: message
+    //       int [0] a;
+    //           ^
+    pub fn render(&self, filename: &str, source: &str) -> String {
+        let span = match self.span {
+        Some(span) => span,
+        None => return format!("{}: {}", filename, self.message),
+        };
+
+        let line_text = source.lines().nth(span.line - 1).unwrap_or("");
+        let caret = " ".repeat(span.column - 1);
+        format!("{}:{}:{}: {}\n{}\n{}^", filename, span.line, span.column, self.message, line_text, caret)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+        Some(span) => write!(f, "{}:{}: {}", span.line, span.column, self.message),
+        None => write!(f, "{}", self.message),
+        }
+    }
+}
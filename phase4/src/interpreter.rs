@@ -1,36 +1,143 @@
-pub fn execute_ir(code: &str) {
+// A program in the intermediate representation, lexed and parsed into bytecode.
+pub struct IRProgram {
+    functions: Vec<FunctionBytecode>,
+}
+
+// Lexes and parses the given IR code, checking that it is well formed.
+pub fn load_ir(code: &str) -> Result<IRProgram, IRError> {
     let tokens = match lex_ir(code) {
     Ok(tokens) => tokens,
-    Err(e) => {
-        println!("***Error. {e}");
-        return;
-    }
+    Err(e) => return Err(IRError::new(e)),
     };
 
-    let bytecode = match parse_ir(&tokens, &mut 0) {
-    Ok(bytecode) => {
-        println!("Valid IR. Executing Generated Bytecode...");
-        bytecode
+    let functions = parse_ir(&tokens, &mut 0)?;
+    return Ok(IRProgram { functions: functions });
+}
+
+// What happens when %add, %sub, %mult, %div or %mod produce a result that does not fit in 32 bits.
+// The same mode gives the same results in debug and release builds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowMode {
+    // the result wraps around, like two's complement hardware
+    Wrapping,
+    // the program stops with a runtime error
+    Checked,
+    // the result is clamped to the smallest or largest integer
+    Saturating,
+}
+
+// Settings for running a program.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub overflow: OverflowMode,
+    // the most function calls that can be running at once, including main.
+    // going deeper is a stack overflow error.
+    pub max_call_depth: usize,
+    // when set, the program is stopped after running this many instructions.
+    pub max_instructions: Option<u64>,
+    // when set, the program is stopped after running for this long.
+    // time spent waiting for input counts too.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RunOptions {
+    fn default() -> RunOptions {
+        return RunOptions {
+            overflow: OverflowMode::Checked,
+            max_call_depth: 100000,
+            max_instructions: None,
+            timeout: None,
+        };
     }
+}
+
+// Runs the main function of a loaded program and returns its exit code.
+// %input reads from input, and %out writes to output.
+pub fn run_ir<I: Input, O: Output>(program: &IRProgram, input: &mut I, output: &mut O, options: &RunOptions) -> Result<i32, IRError> {
+    return run_program(input, output, &program.functions, options);
+}
+
+// The source of the numbers read by %input.
+pub trait Input {
+    // Returns the next number, or an error message when there is nothing left to read.
+    fn read_number(&mut self) -> Result<i32, String>;
+}
+
+// The sink of the numbers written by %out.
+pub trait Output {
+    fn write_number(&mut self, num: i32);
+}
+
+// Reads one number per line from standard input.
+// Lines that are not numbers are reported on standard error and read again.
+pub struct StdinInput {
+    stdin: io::Stdin,
+}
+
+impl StdinInput {
+    pub fn new() -> StdinInput {
+        StdinInput { stdin: io::stdin() }
+    }
+}
+
+impl Default for StdinInput {
+    fn default() -> StdinInput {
+        return StdinInput::new();
+    }
+}
+
+impl Input for StdinInput {
+    fn read_number(&mut self) -> Result<i32, String> {
+        let mut buf = String::with_capacity(64);
+        loop {
+            buf.clear();
+            match self.stdin.read_line(&mut buf) {
+            Ok(0) => {
+                return Err(String::from("Runtime Error. Reached the end of the input."));
+            }
+
+            Ok(_) => {
+                let token = buf.trim();
+                match token.parse::<i32>() {
+                Ok(num) => return Ok(num),
+                Err(_) => eprintln!("User Input Error. '{}' is not a valid number.", token),
+                }
+            }
+
+            Err(e) => {
+                eprintln!("Error. Failed to read from standard input correctly.");
+                eprintln!("{e}");
+                eprintln!("Please try again.");
+            }
 
-    Err(e) => {
-        println!("***Error. Invalid Bytecode.");
-        println!("------------------");
-        for (i, l) in code.lines().enumerate() {
-            println!("{:03}:  {}", i+1, l);
-            if i+1 == e.line {
-                break;
             }
         }
-        println!("------------------");
-        println!("{e}");
-        return;
     }
+}
 
-    };
+// Writes one number per line to standard output.
+pub struct StdoutOutput;
+
+impl Output for StdoutOutput {
+    fn write_number(&mut self, num: i32) {
+        println!("{}", num);
+    }
+}
 
-    let stdin = io::stdin();
-    run_program(&stdin, &bytecode);
+// Numbers are read from the front of the vector.
+impl Input for Vec<i32> {
+    fn read_number(&mut self) -> Result<i32, String> {
+        if self.is_empty() {
+            return Err(String::from("Runtime Error. Reached the end of the input."));
+        }
+        return Ok(self.remove(0));
+    }
+}
+
+impl Output for Vec<String> {
+    fn write_number(&mut self, num: i32) {
+        self.push(num.to_string());
+    }
 }
 
 use std::io;
@@ -63,7 +170,8 @@ fn lex_ir(code: &str) -> Result<Vec<IRTok>, String> {
             tokens.push(token);
         }
 
-        '0'..='9' => {
+        // numbers can be negative, such as '%mov a, -5'
+        '0'..='9' | '-' => {
             let start = i;
             i += 1;
             while i < array.len() {
@@ -76,7 +184,10 @@ fn lex_ir(code: &str) -> Result<Vec<IRTok>, String> {
             }
             let end = i;
             let string_token = &code[start..end];
-            let number_value = string_token.parse::<i32>().unwrap();
+            let number_value = match string_token.parse::<i32>() {
+            Ok(number_value) => number_value,
+            Err(_) => return Err(format!("invalid number '{}'", string_token)),
+            };
             let token = IRTok::Num(number_value);
             tokens.push(token);
         }
@@ -180,8 +291,6 @@ fn lex_ir(code: &str) -> Result<Vec<IRTok>, String> {
     return Ok(tokens);
 }
 
-const MAX_LINE: usize = 2000000;
-
 fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecode>, IRError> {
     let mut serialized_line: usize = 1;
     let mut vector: Vec<FunctionBytecode> = vec![];
@@ -197,7 +306,7 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
         let bytecode = parse_func_ir(&mut serialized_line, tokens, idx)?;
         for func in &vector {
           if func.name.eq(&bytecode.name) {
-            return error(MAX_LINE, format!("Error. Two functions with the same name {}", func.name));
+            return Err(IRError::new(format!("Error. Two functions with the same name {}", func.name)));
           }
         }
         if bytecode.name.eq("main") {
@@ -207,24 +316,24 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
     }
 
     if has_main == false {
-      return error(MAX_LINE, format!("Error. Generated code does not have a main."));
+      return Err(IRError::new(format!("Error. Generated code does not have a main.")));
     }
 
-    // todo: this is not the correct line numbers. but I dunno how to get better line numbers...
     for func_id in 0..vector.len() {
         for instr_id in 0..vector[func_id].body.len() {
             if let Bytecode::Call(r, call, params) = &vector[func_id].body[instr_id] {
+                let line = vector[func_id].locations[instr_id].0;
                 if let IRTok::Var(func_name) = &tokens[*call] {
                     if let Some(call_value) = find_func_id(&vector, func_name) {
                          if params.len() != vector[call_value].parameters {
-                             return error(MAX_LINE, format!("Error. Invalid parameter passing to '{func_name}'. Expected {} number of parameters. Got {} number of parameters.", vector[call_value].parameters, params.len()));
+                             return error(line, format!("Error. Invalid parameter passing to '{func_name}'. Expected {} number of parameters. Got {} number of parameters.", vector[call_value].parameters, params.len()));
                          }
                          vector[func_id].body[instr_id] = Bytecode::Call(*r, call_value, params.to_vec());
                     } else {
-                         return error(MAX_LINE, format!("Error. Undeclared function '{}'", func_name));
+                         return error(line, format!("Error. Undeclared function '{}'", func_name));
                     }
                 } else {
-                    return error(MAX_LINE, format!("Internal Interpreter Error."));
+                    return Err(IRError::new(format!("Internal Interpreter Error.")));
                 }
             }
         }
@@ -242,26 +351,97 @@ fn parse_ir(tokens: &Vec<IRTok>, idx: &mut usize) -> Result< Vec<FunctionBytecod
     }
 }
 
-struct IRError {
-    line: usize,
-    message: String,
+// line is the line of the IR code, or None when the error is about the whole
+// program. runtime errors also tell which function was running, and the line
+// of the source code when the IR has '%line' mappings.
+#[derive(Debug, Clone)]
+pub struct IRError {
+    pub line: Option<usize>,
+    pub message: String,
+    pub function: Option<String>,
+    pub source_line: Option<usize>,
+    // the calls that were running when a runtime error happened, outermost first.
+    pub backtrace: Vec<Frame>,
+    // the number of instructions run before a runtime error happened.
+    pub instructions: u64,
+    // set when the program did not fail, but was stopped by one of the limits in RunOptions.
+    pub limit: Option<ExecutionLimit>,
+}
+
+// The limit that stopped a program that ran for too long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionLimit {
+    Instructions,
+    Timeout,
+}
+
+impl IRError {
+    // An error that is not tied to a line of the IR, such as a missing main function.
+    // Runtime errors are created this way, and given their line by locate_error.
+    fn new(message: String) -> IRError {
+        return IRError {line: None, message: message, function: None, source_line: None, backtrace: vec![], instructions: 0, limit: None};
+    }
+
+    // Shows the backtrace like "main -> fib(3) -> fib(1)".
+    // When there are more than max_depth calls, only main and the innermost
+    // calls are shown, so that deep recursion does not flood the output.
+    pub fn backtrace_string(&self, max_depth: usize) -> String {
+        let mut calls: Vec<String> = vec![];
+        let max_depth = max_depth.max(2);
+        let omitted = self.backtrace.len().saturating_sub(max_depth);
+        for (i, frame) in self.backtrace.iter().enumerate() {
+            if i == 0 || i > omitted {
+                calls.push(frame.to_string());
+            } else if i == 1 {
+                calls.push(format!("... {} more ...", omitted));
+            }
+        }
+        return calls.join(" -> ");
+    }
+}
+
+// A function call on the stack of the interpreter.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: String,
+    // the instruction being run, or the call being waited on.
+    pub instr_pointer: usize,
+    pub arguments: Vec<i32>,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.arguments.is_empty() {
+            return write!(f, "{}", self.function);
+        }
+        let arguments: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.function, arguments.join(", "))
+    }
 }
 
 impl fmt::Display for IRError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.line == MAX_LINE {
-            write!(f, "Error. {}", self.message)
-        } else {
-            write!(f, "Error at line {}. {}", self.line, self.message)
+        let line = match self.line {
+        Some(line) => line,
+        None => return write!(f, "Error. {}", self.message),
+        };
+        write!(f, "Error at line {}", line)?;
+        if let Some(function) = &self.function {
+            write!(f, " in function '{}'", function)?;
+        }
+        if let Some(source_line) = self.source_line {
+            write!(f, " (source line {})", source_line)?;
         }
+        write!(f, ". {}", self.message)
     }
 }
 
 fn error<T>(line: usize, message: String) -> Result<T, IRError> {
-    Err(IRError {line: line, message: message})
+    Err(IRError {line: Some(line), message: message, function: None, source_line: None, backtrace: vec![], instructions: 0, limit: None})
 }
 
 use std::fmt;
+use std::time::{Duration, Instant};
 
 fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usize) -> Result<FunctionBytecode, IRError> {
     let mut function_bytecode = FunctionBytecode {
@@ -270,6 +450,7 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
         id:0,
         variables: HashMap::new(),
         body: vec![],
+        locations: vec![],
     };
 
     if !matches!(tokens[*idx], IRTok::Func) {
@@ -332,12 +513,20 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
 
     let mut labels_hash: HashMap<String, usize> = Default::default();
     let mut line: usize = 0;
+    let mut source_line: Option<usize> = None;
     loop {
         let bytecode = parse_instruction(serialized_line, line, &mut function_bytecode, &mut labels_hash, tokens, idx)?;
         if matches!(bytecode, Bytecode::End) {
             break;
         }
+
+        // '%line' applies to the instructions after it, until the next one.
+        if let Bytecode::SourceLine(n) = bytecode {
+            source_line = Some(n);
+        }
+        // parse_instruction has already moved past the newline of the instruction
         function_bytecode.body.push(bytecode); 
+        function_bytecode.locations.push((*serialized_line - 1, source_line));
         line += 1;
     }
 
@@ -346,6 +535,7 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
     }
 
     function_bytecode.body.push(Bytecode::End); 
+    function_bytecode.locations.push((*serialized_line, source_line));
     for i in 0..function_bytecode.body.len() {
         match &function_bytecode.body[i] {
         Bytecode::Jmp(index) => {
@@ -353,10 +543,10 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::Jmp(*id);
                 } else {
-                    return error(i, format!("Error. invalid label {}", label_name));
+                    return error(function_bytecode.locations[i].0, format!("Error. invalid label {}", label_name));
                 }
             } else {
-                return error(*serialized_line, String::from("Internal Compiler Error."));
+                return error(function_bytecode.locations[i].0, String::from("Internal Compiler Error."));
             }
         }
         Bytecode::BranchIf(tf, index) => {
@@ -364,10 +554,10 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::BranchIf(tf.clone(), *id);
                 } else {
-                    return error(*serialized_line, format!("Error. invalid label {}", label_name));
+                    return error(function_bytecode.locations[i].0, format!("Error. invalid label {}", label_name));
                 }
             } else {
-                return error(*serialized_line, String::from("Internal Compiler Error."));
+                return error(function_bytecode.locations[i].0, String::from("Internal Compiler Error."));
             }
         }
         Bytecode::BranchIfn(tf, index) => {
@@ -375,10 +565,10 @@ fn parse_func_ir(serialized_line: &mut usize, tokens: &Vec<IRTok>, idx: &mut usi
                 if let Some(id) = labels_hash.get(label_name) {
                     function_bytecode.body[i] = Bytecode::BranchIfn(tf.clone(), *id);
                 } else {
-                    return error(*serialized_line, format!("Error. invalid label {}", label_name));
+                    return error(function_bytecode.locations[i].0, format!("Error. invalid label {}", label_name));
                 }
             } else {
-                return error(*serialized_line, String::from("Internal Compiler Error."));
+                return error(function_bytecode.locations[i].0, String::from("Internal Compiler Error."));
             }
         }
         _ => {}
@@ -395,6 +585,8 @@ struct FunctionBytecode {
     id: i32,
     variables: HashMap<String, VariableType>,
     body: Vec<Bytecode>,
+    // the IR line and the source line of each instruction in body.
+    locations: Vec<(usize, Option<usize>)>,
 }
 
 fn get_id(function: &mut FunctionBytecode) -> i32 {
@@ -410,7 +602,7 @@ fn read_integer_value(variables: &HashMap<i32, i32>, op: &Op) -> i32 {
     }
 }
 
-fn run_program(stdin: &io::Stdin, calls: &Vec<FunctionBytecode>) {
+fn run_program<I: Input, O: Output>(input: &mut I, output: &mut O, calls: &Vec<FunctionBytecode>, options: &RunOptions) -> Result<i32, IRError> {
     let entry_point = {
         let mut result = None;
         for func in calls {
@@ -421,62 +613,211 @@ fn run_program(stdin: &io::Stdin, calls: &Vec<FunctionBytecode>) {
         match result {
         Some(r) => r,
         None => {
-            println!("Runtime Error. No main function declared.");
-            return;
+            return Err(IRError::new(format!("Runtime Error. No main function declared.")));
         }
         }
     };
-    match run_bytecode(stdin, entry_point, calls, &vec![]) {
-    Ok(n) => println!("Run successful. Exit code {}", n),
-    Err(e) => println!("{}", e),
+
+    // the call stack lives on the heap, so deep recursion in the program
+    // cannot overflow the stack of the interpreter itself.
+    let mut stack: Vec<CallFrame> = vec![CallFrame::new(entry_point, vec![])];
+    let mut meter = Meter { instructions: 0, start: Instant::now() };
+    loop {
+        let frame = stack.last_mut().unwrap();
+        match execute(input, output, frame, options, &mut meter) {
+        Err(e) => return Err(locate_error(&e, &stack, &meter)),
+
+        Ok(Control::Call(function_index, arguments)) => {
+            let function = &calls[function_index];
+            if stack.len() >= options.max_call_depth {
+                let e = format!("Runtime Error. Stack overflow. Calling '{}' would go past the maximum call depth of {}.", function.name, options.max_call_depth);
+                return Err(locate_error(&IRError::new(e), &stack, &meter));
+            }
+            if arguments.len() != function.parameters {
+                let e = format!("Runtime Error. Incorrect number of parameters passed to the function. Expected {}, got {} parameters", function.parameters, arguments.len());
+                return Err(locate_error(&IRError::new(e), &stack, &meter));
+            }
+            stack.push(CallFrame::new(function, arguments));
+        }
+
+        Ok(Control::Return(value)) => {
+            stack.pop();
+            let caller = match stack.last_mut() {
+            Some(caller) => caller,
+            None => return Ok(value),
+            };
+
+            // the caller is still on its %call instruction
+            if let Bytecode::Call(Some(dest), _, _) = &caller.function.body[caller.instr_pointer] {
+                let dest = caller.variables.get_mut(dest).unwrap();
+                *dest = value;
+            }
+            caller.instr_pointer += 1;
+        }
+        }
     }
 }
 
-fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<FunctionBytecode>, parameters: &Vec<i32>) -> Result<i32, IRError>  {
-    let mut variables: HashMap<i32, i32> = HashMap::new();
-    let mut arrays: HashMap<i32, Vec<i32>> = HashMap::new();
+// A 32 bit integer can only be shifted by 0 to 31 bits
+fn check_shift(amount: i32) -> Result<(), IRError> {
+    if !(0..=31).contains(&amount) {
+        let e = format!("Runtime Error. Shift amount {} is out of range. It must be between 0 and 31.", amount);
+        return Err(IRError::new(e));
+    }
+    return Ok(());
+}
 
-    // setup local variables
-    for (_, vartype) in &function.variables {
-         match vartype {
-         VariableType::IntVar(id) => {
-             variables.insert(*id, 0);
-         }
+// Picks the result of an arithmetic operation that may have overflowed.
+// checked is None when the exact result does not fit in 32 bits.
+fn overflow_result(mode: OverflowMode, checked: Option<i32>, wrapping: i32, saturating: i32, num1: i32, op: &str, num2: i32) -> Result<i32, IRError> {
+    match mode {
+    OverflowMode::Wrapping => return Ok(wrapping),
+    OverflowMode::Saturating => return Ok(saturating),
+    OverflowMode::Checked => {
+        match checked {
+        Some(num) => return Ok(num),
+        None => {
+            let e = format!("Runtime Error. Integer overflow in {} {} {}.", num1, op, num2);
+            return Err(IRError::new(e));
+        }
+        }
+    }
+    }
+}
 
-         VariableType::ArrayVar(id, len) => {
-             // create an array of zeroes.
-             let arr = vec![0i32; *len as usize];
-             arrays.insert(*id, arr);
-         }
+// A function call on the stack of the interpreter, with the local variables of the function.
+struct CallFrame<'a> {
+    function: &'a FunctionBytecode,
+    variables: HashMap<i32, i32>,
+    arrays: HashMap<i32, Vec<i32>>,
+    instr_pointer: usize,
+    arguments: Vec<i32>,
+}
 
-         }
+impl<'a> CallFrame<'a> {
+    fn new(function: &'a FunctionBytecode, arguments: Vec<i32>) -> CallFrame<'a> {
+        let mut variables: HashMap<i32, i32> = HashMap::new();
+        let mut arrays: HashMap<i32, Vec<i32>> = HashMap::new();
+
+        // setup local variables
+        for (_, vartype) in &function.variables {
+             match vartype {
+             VariableType::IntVar(id) => {
+                 variables.insert(*id, 0);
+             }
+
+             VariableType::ArrayVar(id, len) => {
+                 // create an array of zeroes.
+                 let arr = vec![0i32; *len as usize];
+                 arrays.insert(*id, arr);
+             }
+
+             }
+        }
+
+        // hopefully this covers everything needed for parameter passing...
+        for (i, value) in arguments.iter().enumerate() {
+            let variable = variables.get_mut(&(i as i32)).unwrap();
+            *variable = *value;
+        }
+
+        return CallFrame {
+            function: function,
+            variables: variables,
+            arrays: arrays,
+            instr_pointer: 0,
+            arguments: arguments,
+        };
+    }
+}
+
+// Why execute() stopped running the instructions of a function.
+enum Control {
+    // the function index and the arguments of a %call
+    Call(usize, Vec<i32>),
+    Return(i32),
+}
+
+// Counts the instructions run by the program, and when it started running.
+struct Meter {
+    instructions: u64,
+    start: Instant,
+}
+
+// Stops the program when it has used up its instruction budget or its time.
+// The clock is only read every so often, as it is much slower than running an instruction.
+fn check_limits(meter: &Meter, options: &RunOptions) -> Result<(), IRError> {
+    let mut limit = None;
+    if let Some(max_instructions) = options.max_instructions {
+        if meter.instructions >= max_instructions {
+            limit = Some(ExecutionLimit::Instructions);
+        }
     }
-    if parameters.len() != function.parameters {
-         let e = format!("Runtime Error. Incorrect number of parameters passed to the function. Expected {}, got {} parameters", function.parameters, parameters.len());
-         return error(MAX_LINE, e);
+    if let Some(timeout) = options.timeout {
+        if meter.instructions.is_multiple_of(1024) && meter.start.elapsed() >= timeout {
+            limit = Some(ExecutionLimit::Timeout);
+        }
     }
 
-    // hopefully this covers everything needed for parameter passing...
-    for (i, value) in parameters.iter().enumerate() {
-        let variable = variables.get_mut(&(i as i32)).unwrap();
-        *variable = *value;
+    match limit {
+    None => return Ok(()),
+    Some(limit) => {
+        let mut e = IRError::new(format!("Execution limit exceeded. The program was stopped after {} instructions.", meter.instructions));
+        e.limit = Some(limit);
+        return Err(e);
+    }
     }
+}
 
-    // a lot of unwraps, but we already checked everything
-    // so this should work.
-    // execute instructions.
-    let mut instr_pointer: usize = 0;
+// Adds the IR line, the function name and the source line of the current instruction
+// to a runtime error, along with the calls that led to it.
+fn locate_error(e: &IRError, stack: &Vec<CallFrame>, meter: &Meter) -> IRError {
+    let frame = stack.last().unwrap();
+    let (line, source_line) = frame.function.locations[frame.instr_pointer];
+    let mut backtrace: Vec<Frame> = vec![];
+    for frame in stack {
+        backtrace.push(Frame {
+            function: frame.function.name.clone(),
+            instr_pointer: frame.instr_pointer,
+            arguments: frame.arguments.clone(),
+        });
+    }
+    return IRError {
+        line: Some(line),
+        message: e.message.clone(),
+        function: Some(frame.function.name.clone()),
+        source_line: source_line,
+        backtrace: backtrace,
+        instructions: meter.instructions,
+        limit: e.limit,
+    };
+}
+
+// a lot of unwraps, but we already checked everything
+// so this should work.
+// execute instructions until the function calls another one or returns.
+// on an error, instr_pointer is left at the failing instruction.
+fn execute<I: Input, O: Output>(input: &mut I, output: &mut O, frame: &mut CallFrame, options: &RunOptions, meter: &mut Meter) -> Result<Control, IRError> {
+    let function = frame.function;
+    let variables = &mut frame.variables;
+    let arrays = &mut frame.arrays;
+    let instr_pointer = &mut frame.instr_pointer;
     loop {
-        let instr = &function.body[instr_pointer];
+        let instr = &function.body[*instr_pointer];
+        // labels and source line mappings do nothing, so they are not counted
+        if !matches!(instr, Bytecode::Label | Bytecode::SourceLine(_)) {
+            check_limits(meter, options)?;
+            meter.instructions += 1;
+        }
         match instr {
         Bytecode::End => {
-            break;
+            return Ok(Control::Return(0));
         }
 
         Bytecode::Int(id) => {
             let value = variables.get_mut(id).unwrap();
             *value = 0;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::IntArray(id, len) => {
@@ -485,52 +826,30 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
                  let idx = i as usize;
                  array[idx] ^= array[idx];
             }
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::Out(value) => {
             let num = read_integer_value(&variables, value);
-            println!("{}", num);
-            instr_pointer += 1;
+            output.write_number(num);
+            *instr_pointer += 1;
         }
 
         Bytecode::In(id) => {
-            let mut buf = String::with_capacity(64);
-            loop {
-                match stdin.read_line(&mut buf) {
-                Ok(_) => {
-                     let token = buf.trim_end();
-                     match token.parse::<i32>() {
-                     Ok(num) => {
-                          let dest = variables.get_mut(id).unwrap();
-                          *dest = num;
-                          break;
-                     }
-
-                     Err(_) => {
-                         println!("User Input Error. '{}' is not a valid number.", token);
-                         buf.clear();
-                     }
-
-                     }
-                }
-
-                Err(e) => {
-                     println!("Error. Failed to read from standard input correctly.");
-                     println!("{e}");
-                     println!("Please try again.");
-                }
-
-                }
-            }
-            instr_pointer += 1;
+            let num = match input.read_number() {
+            Ok(num) => num,
+            Err(e) => return Err(IRError::new(e)),
+            };
+            let dest = variables.get_mut(id).unwrap();
+            *dest = num;
+            *instr_pointer += 1;
         }
 
         Bytecode::Mov(MemWrite::IntVar(dest), src) => {
             let num = read_memory(&variables, &arrays, src)?;
             let dest = variables.get_mut(dest).unwrap();
             *dest = num;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::Mov(MemWrite::ArrayWrite(dest, index), src) => {
@@ -539,15 +858,15 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let index = read_integer_value(&variables, index);
             if index < 0 {
                 let e = format!("Runtime Error: Array out of bounds. Value {}. Array Length {}", index, dest.len());
-                return error(MAX_LINE, e);
+                return Err(IRError::new(e));
             }
             let i = index as usize;
             if i < dest.len() {
                 dest[i] = num;
-                instr_pointer += 1;
+                *instr_pointer += 1;
             } else {
                 let e = format!("Runtime Error: Array out of bounds. Value {}. Array Length {}", i, dest.len());
-                return error(MAX_LINE, e);
+                return Err(IRError::new(e));
             }
         }
 
@@ -555,48 +874,99 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num1 = read_integer_value(&variables, src1);
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 + num2;
-            instr_pointer += 1;
+            *dest = overflow_result(options.overflow, num1.checked_add(num2), num1.wrapping_add(num2), num1.saturating_add(num2), num1, "+", num2)?;
+            *instr_pointer += 1;
         }
 
         Bytecode::Sub(dest, src1, src2) => {
             let num1 = read_integer_value(&variables, src1);
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 - num2;
-            instr_pointer += 1;
+            *dest = overflow_result(options.overflow, num1.checked_sub(num2), num1.wrapping_sub(num2), num1.saturating_sub(num2), num1, "-", num2)?;
+            *instr_pointer += 1;
         }
 
         Bytecode::Mult(dest, src1, src2) => {
             let num1 = read_integer_value(&variables, src1);
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 * num2;
-            instr_pointer += 1;
+            *dest = overflow_result(options.overflow, num1.checked_mul(num2), num1.wrapping_mul(num2), num1.saturating_mul(num2), num1, "*", num2)?;
+            *instr_pointer += 1;
         }
 
         Bytecode::Div(dest, src1, src2) => {
             let num1 = read_integer_value(&variables, src1);
             let num2 = read_integer_value(&variables, src2);
             if num2 == 0 {
-                let e = String::from("Error. Attempt to divide by zero.");
-                return error(MAX_LINE, e);
+                let e = String::from("Runtime Error. Attempt to divide by zero.");
+                return Err(IRError::new(e));
             }
             let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 / num2;
-            instr_pointer += 1;
+            // i32::MIN / -1 is the only division that overflows
+            *dest = overflow_result(options.overflow, num1.checked_div(num2), num1.wrapping_div(num2), num1.saturating_div(num2), num1, "/", num2)?;
+            *instr_pointer += 1;
         }
 
         Bytecode::Mod(dest, src1, src2) => {
             let num1 = read_integer_value(&variables, src1);
             let num2 = read_integer_value(&variables, src2);
             if num2 == 0 {
-                let e = String::from("Error. Attempt to divide by zero.");
-                return error(MAX_LINE, e);
+                let e = String::from("Runtime Error. Attempt to divide by zero.");
+                return Err(IRError::new(e));
             }
             let dest = variables.get_mut(dest).unwrap();
-            *dest = num1 % num2;
-            instr_pointer += 1;
+            // i32::MIN % -1 is 0, but computing it overflows on most hardware
+            *dest = overflow_result(options.overflow, num1.checked_rem(num2), num1.wrapping_rem(num2), num1.wrapping_rem(num2), num1, "%", num2)?;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::And(dest, src1, src2) => {
+            let num1 = read_integer_value(&variables, src1);
+            let num2 = read_integer_value(&variables, src2);
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = num1 & num2;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::Or(dest, src1, src2) => {
+            let num1 = read_integer_value(&variables, src1);
+            let num2 = read_integer_value(&variables, src2);
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = num1 | num2;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::Xor(dest, src1, src2) => {
+            let num1 = read_integer_value(&variables, src1);
+            let num2 = read_integer_value(&variables, src2);
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = num1 ^ num2;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::Shl(dest, src1, src2) => {
+            let num1 = read_integer_value(&variables, src1);
+            let num2 = read_integer_value(&variables, src2);
+            check_shift(num2)?;
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = num1 << num2;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::Shr(dest, src1, src2) => {
+            let num1 = read_integer_value(&variables, src1);
+            let num2 = read_integer_value(&variables, src2);
+            check_shift(num2)?;
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = num1 >> num2;
+            *instr_pointer += 1;
+        }
+
+        Bytecode::Not(dest, src) => {
+            let num = read_integer_value(&variables, src);
+            let dest = variables.get_mut(dest).unwrap();
+            *dest = !num;
+            *instr_pointer += 1;
         }
 
         Bytecode::LessThan(dest, src1, src2) => {
@@ -604,7 +974,7 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 < num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::LessEqual(dest, src1, src2) => {
@@ -612,7 +982,7 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 <= num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::Equal(dest, src1, src2) => {
@@ -620,7 +990,7 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 == num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::NotEqual(dest, src1, src2) => {
@@ -628,7 +998,7 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 != num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::GreaterThan(dest, src1, src2) => {
@@ -636,7 +1006,7 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 > num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
         Bytecode::GreaterEqual(dest, src1, src2) => {
@@ -644,25 +1014,25 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
             let num2 = read_integer_value(&variables, src2);
             let dest = variables.get_mut(dest).unwrap();
             *dest = (num1 >= num2) as i32;
-            instr_pointer += 1;
+            *instr_pointer += 1;
         }
 
-        Bytecode::Label => {
-            instr_pointer += 1;
+        Bytecode::Label | Bytecode::SourceLine(_) => {
+            *instr_pointer += 1;
         }
 
         Bytecode::Jmp(jump) => {
-            instr_pointer = *jump;
+            *instr_pointer = *jump;
         }
 
         Bytecode::BranchIf(src1, jump) => {
             let num1 = read_integer_value(&variables, src1);
             match num1 {
-            0 => {instr_pointer += 1;}
-            1 => {instr_pointer = *jump;}
+            0 => {*instr_pointer += 1;}
+            1 => {*instr_pointer = *jump;}
             _ => {
                 let e = format!("Runtime Error. Branch on a variable that is neither 0 or 1. The value is: {}", num1);
-                return error(MAX_LINE, e);
+                return Err(IRError::new(e));
             }
             }
         }
@@ -670,38 +1040,32 @@ fn run_bytecode(stdin: &io::Stdin, function: &FunctionBytecode, calls: &Vec<Func
         Bytecode::BranchIfn(src1, jump) => {
             let num1 = read_integer_value(&variables, src1);
             match num1 {
-            0 => {instr_pointer = *jump;}
-            1 => {instr_pointer += 1;}
+            0 => {*instr_pointer = *jump;}
+            1 => {*instr_pointer += 1;}
             _ => {
                 let e = format!("Runtime Error. Branch on a variable that is neither 0 or 1. The value is: {}", num1);
-                return error(MAX_LINE, e);
+                return Err(IRError::new(e));
             }
             }
         }
 
-        Bytecode::Call(dest, function_index, parameters) => {
-             let function = &calls[*function_index];
+        // the return value is stored by run_program, which then moves past the %call
+        Bytecode::Call(_, function_index, parameters) => {
              let mut pass = vec![];
              for p in parameters {
                   let num1: i32 = read_integer_value(&variables, p);
                   pass.push(num1);
              }
-
-             let eax = run_bytecode(stdin, function, calls, &pass)?;
-             let dest = variables.get_mut(dest).unwrap();
-             *dest = eax;
-             instr_pointer += 1;
+             return Ok(Control::Call(*function_index, pass));
         }
 
         Bytecode::Return(src1) => {
             let num = read_integer_value(&variables, src1);
-            return Ok(num);
+            return Ok(Control::Return(num));
         }
 
         }
     }
-
-    return Ok(0);
 }
 
 fn lookup_integer_variable_id(line: usize, function: &FunctionBytecode, ident: &String) -> Result<Op, IRError> {
@@ -803,13 +1167,18 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
     // function calling routines.
     IRTok::Call => {
         *idx += 1;
-        let op = match next_result(tokens, idx) {
-        IRTok::Var(ident) => lookup_variable_dest_id(*serialized_line, function, ident)?,
-        _ => return error(*serialized_line, String::from("invalid instruction. expected format like '%call value, function(a,b)'")),
-        };
+        // the destination can be left out, as in '%call function(a,b)',
+        // when the return value is not needed.
+        let mut op = None;
+        if !matches!(tokens.get(*idx + 1), Some(IRTok::LParen)) {
+            op = match next_result(tokens, idx) {
+            IRTok::Var(ident) => Some(lookup_variable_dest_id(*serialized_line, function, ident)?),
+            _ => return error(*serialized_line, String::from("invalid instruction. expected format like '%call value, function(a,b)'")),
+            };
 
-        if !matches!(next_result(tokens, idx), IRTok::Comma) {
-            return error(*serialized_line, String::from("invalid instruction. missing comma ',' in between '%call value, function(a,b)'"));
+            if !matches!(next_result(tokens, idx), IRTok::Comma) {
+                return error(*serialized_line, String::from("invalid instruction. missing comma ',' in between '%call value, function(a,b)'"));
+            }
         }
 
         let func_id = match tokens[*idx] {
@@ -1078,6 +1447,56 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
         bytecode = Bytecode::Mod(dest, src1, src2);
     }
 
+    // bitwise operators.
+    IRTok::And => {
+        *idx += 1;
+        let (dest, src1, src2) = addr_code3(*serialized_line, function, tokens, idx)?;
+        bytecode = Bytecode::And(dest, src1, src2);
+    }
+
+    IRTok::Or => {
+        *idx += 1;
+        let (dest, src1, src2) = addr_code3(*serialized_line, function, tokens, idx)?;
+        bytecode = Bytecode::Or(dest, src1, src2);
+    }
+
+    IRTok::Xor => {
+        *idx += 1;
+        let (dest, src1, src2) = addr_code3(*serialized_line, function, tokens, idx)?;
+        bytecode = Bytecode::Xor(dest, src1, src2);
+    }
+
+    IRTok::Shl => {
+        *idx += 1;
+        let (dest, src1, src2) = addr_code3(*serialized_line, function, tokens, idx)?;
+        bytecode = Bytecode::Shl(dest, src1, src2);
+    }
+
+    IRTok::Shr => {
+        *idx += 1;
+        let (dest, src1, src2) = addr_code3(*serialized_line, function, tokens, idx)?;
+        bytecode = Bytecode::Shr(dest, src1, src2);
+    }
+
+    IRTok::Not => {
+        *idx += 1;
+        let dest = match next_result(tokens, idx) {
+        IRTok::Var(ident) => lookup_variable_dest_id(*serialized_line, function, ident)?,
+        _ => return error(*serialized_line, String::from("invalid instruction. expected format like '%not dest, src'")),
+        };
+
+        if !matches!(next_result(tokens, idx), IRTok::Comma) {
+            return error(*serialized_line, String::from("invalid instruction. missing comma. expected format like '%not dest, src'"));
+        }
+
+        let src = match next_result(tokens, idx) {
+        IRTok::Var(ident) => lookup_integer_variable_id(*serialized_line, function, ident)?,
+        IRTok::Num(num) => Op::Num(*num),
+        _ => return error(*serialized_line, String::from("invalid instruction. expected format like '%not dest, src'")),
+        };
+        bytecode = Bytecode::Not(dest, src);
+    }
+
     // comparison operators.
     IRTok::LessThan => {
         *idx += 1;
@@ -1129,6 +1548,17 @@ fn parse_instruction(serialized_line: &mut usize, line: usize, function: &mut Fu
         }
     }
 
+    IRTok::SourceLine => {
+        *idx += 1;
+        match next_result(tokens, idx) {
+        IRTok::Num(n) if *n > 0 => {
+            bytecode = Bytecode::SourceLine(*n as usize);
+        }
+
+        _ => return error(*serialized_line, String::from("invalid instruction. expected format like '%line 10'")),
+        }
+    }
+
     IRTok::Label(name) => {
         *idx += 1;
         if let None = labels_hash.get(name) {
@@ -1243,9 +1673,13 @@ fn addr_code3(serialized_line: usize, function: &FunctionBytecode, tokens: &Vec<
     return Ok((dest, src1, src2));
 }
 
+// Returns the current token and moves to the next one.
+// The index never moves past IRTok::End, so truncated IR gives an error instead of reading past the tokens.
 fn next_result<'a>(tokens: &'a Vec<IRTok>, index: &mut usize) -> &'a IRTok {
     let ret = *index;
-    *index += 1;
+    if !matches!(tokens[ret], IRTok::End) {
+        *index += 1;
+    }
     &tokens[ret]
 }
 
@@ -1267,6 +1701,12 @@ fn opcode(s: &str) -> Result<IRTok, String> {
     "%mult" => Ok(Mult),
     "%div" => Ok(Div),
     "%mod" => Ok(Mod),
+    "%and" => Ok(And),
+    "%or" => Ok(Or),
+    "%xor" => Ok(Xor),
+    "%shl" => Ok(Shl),
+    "%shr" => Ok(Shr),
+    "%not" => Ok(Not),
     "%lt" => Ok(LessThan),
     "%le" => Ok(LessEqual),
     "%neq" => Ok(NotEqual),
@@ -1274,6 +1714,7 @@ fn opcode(s: &str) -> Result<IRTok, String> {
     "%gt" => Ok(GreaterThan),
     "%ge" => Ok(GreaterEqual),
     "%jmp" => Ok(Jump),
+    "%line" => Ok(SourceLine),
     "%branch_if" => Ok(BranchIf),
     "%branch_ifn" => Ok(BranchIfNot),
     _ => Err(format!("Invalid Instruction '{}'", s)),
@@ -1287,19 +1728,28 @@ mod ir_tests {
    #[test]
     fn ir_lex() {
         {
-            let toks = lex_ir("%add a, b, c\n");
-            assert!(toks.len() == 6);
+            let toks = lex_ir("%add a, b, c\n").unwrap();
+            assert!(toks.len() == 8);
             assert!(matches!(toks[0], IRTok::Add));
             assert!(matches!(toks[1], IRTok::Var(_)));
             assert!(matches!(toks[2], IRTok::Comma));
             assert!(matches!(toks[3], IRTok::Var(_)));
             assert!(matches!(toks[4], IRTok::Comma));
             assert!(matches!(toks[5], IRTok::Var(_)));
+            assert!(matches!(toks[6], IRTok::EndInstr));
+            assert!(matches!(toks[7], IRTok::End));
         }
 
         {
-            let toks = lex_ir("%func main,,,\n");
-            assert!(toks.len() == 5);
+            let toks = lex_ir("%mov a, -2147483648\n").unwrap();
+            assert!(toks.len() == 6);
+            assert!(matches!(toks[3], IRTok::Num(i32::MIN)));
+            assert!(lex_ir("%mov a, -2147483649\n").is_err());
+        }
+
+        {
+            let toks = lex_ir("%func main,,,\n").unwrap();
+            assert!(toks.len() == 7);
             assert!(matches!(toks[0], IRTok::Func));
             assert!(matches!(toks[1], IRTok::Var(_)));
             assert!(matches!(toks[2], IRTok::Comma));
@@ -1308,8 +1758,8 @@ mod ir_tests {
         }
 
         {
-            let toks = lex_ir("%func,main,,,\n");
-            assert!(toks.len() == 6);
+            let toks = lex_ir("%func,main,,,\n").unwrap();
+            assert!(toks.len() == 8);
             assert!(matches!(toks[0], IRTok::Func));
             assert!(matches!(toks[1], IRTok::Comma));
             assert!(matches!(toks[2], IRTok::Var(_)));
@@ -1319,8 +1769,8 @@ mod ir_tests {
         }
 
         {
-            let toks = lex_ir("%mov [arr+0], 100\n");
-            assert!(toks.len() == 8);
+            let toks = lex_ir("%mov [arr+0], 100\n").unwrap();
+            assert!(toks.len() == 10);
             assert!(matches!(toks[0], IRTok::Mov));
             assert!(matches!(toks[1], IRTok::LBrace));
             assert!(matches!(toks[2], IRTok::Var(_)));
@@ -1331,6 +1781,47 @@ mod ir_tests {
             assert!(matches!(toks[7], IRTok::Num(100)));
         }
     }
+
+    // runs "%op _result, a, b" in each overflow mode
+    fn arithmetic(op: &str, a: i32, b: i32, mode: OverflowMode) -> Result<i32, IRError> {
+        let code = format!("%func main()\n%int _result\n{} _result, {}, {}\n%ret _result\n%endfunc\n", op, a, b);
+        let program = load_ir(&code).unwrap();
+        let mut output: Vec<String> = vec![];
+        return run_ir(&program, &mut vec![], &mut output, &RunOptions { overflow: mode, ..RunOptions::default() });
+    }
+
+    #[test]
+    fn overflow_modes() {
+        use OverflowMode::*;
+        assert!(arithmetic("%add", i32::MAX, 1, Wrapping).unwrap() == i32::MIN);
+        assert!(arithmetic("%add", i32::MAX, 1, Saturating).unwrap() == i32::MAX);
+        let error = arithmetic("%add", i32::MAX, 1, Checked).err().unwrap();
+        assert!(error.message == "Runtime Error. Integer overflow in 2147483647 + 1.");
+
+        assert!(arithmetic("%sub", i32::MIN, 1, Wrapping).unwrap() == i32::MAX);
+        assert!(arithmetic("%sub", i32::MIN, 1, Saturating).unwrap() == i32::MIN);
+        assert!(arithmetic("%sub", 0, i32::MIN, Checked).is_err());
+
+        assert!(arithmetic("%mult", 65536, 65536, Wrapping).unwrap() == 0);
+        assert!(arithmetic("%mult", 65536, -65536, Saturating).unwrap() == i32::MIN);
+        assert!(arithmetic("%mult", 65536, 65536, Checked).is_err());
+
+        assert!(arithmetic("%div", i32::MIN, -1, Wrapping).unwrap() == i32::MIN);
+        assert!(arithmetic("%div", i32::MIN, -1, Saturating).unwrap() == i32::MAX);
+        assert!(arithmetic("%div", i32::MIN, -1, Checked).is_err());
+
+        assert!(arithmetic("%mod", i32::MIN, -1, Wrapping).unwrap() == 0);
+        assert!(arithmetic("%mod", i32::MIN, -1, Saturating).unwrap() == 0);
+        assert!(arithmetic("%mod", i32::MIN, -1, Checked).is_err());
+
+        // results that fit are the same in every mode
+        for mode in [Wrapping, Checked, Saturating] {
+            assert!(arithmetic("%add", 2, 3, mode).unwrap() == 5);
+            assert!(arithmetic("%div", -7, 2, mode).unwrap() == -3);
+            assert!(arithmetic("%mod", -7, 2, mode).unwrap() == -1);
+            assert!(arithmetic("%div", 1, 0, mode).is_err());
+        }
+    }
 }
 
 
@@ -1359,6 +1850,14 @@ enum IRTok {
     Div,
     Mod,
 
+    // bitwise operators.
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Not,
+
     // comparison operators.
     LessThan,
     LessEqual,
@@ -1370,6 +1869,7 @@ enum IRTok {
     // labels/branching
     Label(String),
     Jump,
+    SourceLine,
     BranchIf,
     BranchIfNot,
 
@@ -1420,7 +1920,7 @@ fn read_memory(variables: &HashMap<i32, i32>, arrays: &HashMap<i32, Vec<i32>>, r
         if variable >= 0 && (variable as usize) < array.len() {
             Ok(array[variable as usize])
         } else {
-            error(MAX_LINE, format!("Runtime Error: Array out of bounds. Index {}. Array Length {}.", variable, array.len()))
+            Err(IRError::new(format!("Runtime Error: Array out of bounds. Index {}. Array Length {}.", variable, array.len())))
         }
     }
     }
@@ -1432,6 +1932,8 @@ enum Bytecode {
     // EndFunc
     End,
     Label,
+    // the line of the source code that the next instructions come from.
+    SourceLine(usize),
 
     // declarations.
     Int(i32),
@@ -1449,8 +1951,16 @@ enum Bytecode {
     Div(i32, Op, Op),
     Mod(i32, Op, Op),
 
-    // calling functions.
-    Call(i32, usize, Vec<Op>),
+    // bitwise operators. shifts are arithmetic, and Not is a bitwise complement.
+    And(i32, Op, Op),
+    Or(i32, Op, Op),
+    Xor(i32, Op, Op),
+    Shl(i32, Op, Op),
+    Shr(i32, Op, Op),
+    Not(i32, Op),
+
+    // calling functions. the destination is None when the return value is discarded.
+    Call(Option<i32>, usize, Vec<Op>),
 
     // comparison operators.
     LessThan(i32, Op, Op),
@@ -0,0 +1,460 @@
+use crate::diagnostic::{Diagnostic, Span};
+
+#[derive(Debug, Clone)]
+pub struct Token {
+  pub kind: TokenKind,
+  pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum TokenKind {
+  Func,
+  Return,
+  Int,
+  Print,
+  Read,
+  While,
+  For,
+  Do,
+  If,
+  Else,
+  Break,
+  Continue,
+  LeftParen,
+  RightParen,
+  LeftCurly,
+  RightCurly,
+  LeftBracket,
+  RightBracket,
+  Comma,
+  Semicolon,
+  Plus,
+  Subtract,
+  Multiply,
+  Divide,
+  Modulus,
+  BitAnd,
+  BitOr,
+  BitXor,
+  BitNot,
+  ShiftLeft,
+  ShiftRight,
+  Assign,
+  PlusAssign,
+  SubtractAssign,
+  MultiplyAssign,
+  DivideAssign,
+  ModulusAssign,
+  Increment,
+  Decrement,
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+  Equality,
+  NotEqual,
+  And,
+  Or,
+  Not,
+  // The lexer does not know about the sign of a number, so the value can be up
+  // to 2147483648, the magnitude of i32::MIN. The parser checks the range.
+  Num(i64),
+  Ident(String),
+  End,
+}
+
+// Characters that start an operator, and so can directly follow a number or an identifier
+fn is_operator(c: char) -> bool {
+  return c == '+' || c == '-' || c == '*' || c == '/' || c == '%' || c == '=' || c == '<' || c == '>' || c == '!' || c == '&' || c == '|' || c == '^' || c == '~';
+}
+
+// The end of the character that starts at byte i, which can be several bytes long
+fn char_end(code: &str, i: usize) -> usize {
+  return i + code[i..].chars().next().map_or(1, |c| c.len_utf8());
+}
+
+// This is a lexer that parses numbers/identifiers and math operations
+// Only ASCII characters are skipped over one byte at a time, so i is always at the start of a character.
+pub fn lex(code: &str) -> Result<Vec<Token>, Diagnostic> {
+  let bytes = code.as_bytes();
+  let mut tokens: Vec<Token> = vec![];
+
+  // Keep track of the current line, so every token knows where it came from
+  let mut line = 1;
+  let mut line_start = 0;
+  let mut scanned = 0;
+
+  let mut i = 0;
+  while i < bytes.len() {
+    let c = bytes[i] as char;
+
+    // Count the newlines skipped since the last token
+    while scanned < i {
+      if bytes[scanned] == b'\n' {
+        line += 1;
+        line_start = scanned + 1;
+      }
+      scanned += 1;
+    }
+    let span = Span { offset: i, line: line, column: i - line_start + 1 };
+
+    match c {
+
+    // Digits
+    // If characters among digits, return error message
+    '0'..='9' => {
+      let start = i;
+      i += 1;
+      while i < bytes.len() {
+        let digit = bytes[i] as char;
+        if digit >= '0' && digit <= '9' {
+          i += 1;
+        } else if digit == ' ' || digit == '\n' {
+          // If reached here, means all digits until space or newline
+          break;
+        } else if digit == '(' || digit == ')' || digit == '{' || digit == '}' || digit == '[' || digit == ']'{
+          // If reached here, similar logic as above, all digits are legal until meeting a type of bracket
+          break;
+        } else if digit == ',' || digit == ';'{
+          // If reached here, similar logic as above, all digits are legal until meeting a type of comma
+          break;
+        } else if is_operator(digit) {
+          // The same goes for operators, as in 1+2
+          break;
+        } else {
+          // If current character is a alphabet or any other unrecognized character
+          // Return error message
+          let end = char_end(code, i);
+          let string_token = &code[start..end];
+          return Err(Diagnostic::new(span, format!("Detect invalid identifier {}", string_token)));
+        }
+      }
+      let end = i;
+      let string_token = &code[start..end];
+      let number_value = match string_token.parse::<i64>() {
+      Ok(number_value) if number_value <= -(i32::MIN as i64) => number_value,
+      _ => return Err(Diagnostic::new(span, format!("integer literal {} is out of range", string_token))),
+      };
+      let token = Token { kind: TokenKind::Num(number_value), span: span };
+      tokens.push(token);
+    }
+   
+    // Characters
+    // If character-made string does not match any keywords
+    // It is considered a Identifier
+    'a'..='z' | 'A'..='Z' => {
+      let start = i;
+      i += 1;
+      while i < bytes.len() {
+        let curr = bytes[i] as char;
+        // Variables begin with an upper or lower case letters A-Z followed by a sequence of underscores or numbers.
+        // reference: https://stackoverflow.com/questions/29873569/check-whether-a-char-is-a-letter-or-a-number
+        if curr.is_ascii_alphanumeric() || curr == '_'{
+          i += 1;
+        } else if curr == ' ' || curr == '\n' {
+          // If reached here, means all characters are legal until space or newline
+          break;
+        } else if curr == '(' || curr == ')' || curr == '{' || curr == '}' || curr == '[' || curr == ']'{
+          // If reached here, similar logic as above, all characters are legal until meeting a type of bracket
+          break;
+        } else if curr == ',' || curr == ';'{
+          // If reached here, similar logic as above, all characters are legal until meeting a type of comma
+          break;
+        } else if is_operator(curr) {
+          // The same goes for operators, as in x++ or x+=1
+          break;
+        } else {
+          // If current character is a unrecognized, return error message
+          let end = char_end(code, i);
+          let string_token = &code[start..end];
+          return Err(Diagnostic::new(span, format!("Detect invalid identifier {}", string_token)));
+        }
+      }
+      let end = i;
+      let string_token = &code[start..end];
+      // Check if this parsed string is a predefined keyword
+      match string_token {
+        "func" => {
+          tokens.push(Token { kind: TokenKind::Func, span: span });
+        }
+        "return" => {
+          tokens.push(Token { kind: TokenKind::Return, span: span });
+        }
+        "int" => {
+          tokens.push(Token { kind: TokenKind::Int, span: span });
+        }
+        "print" => {
+          tokens.push(Token { kind: TokenKind::Print, span: span });
+        }
+        "read" => {
+          tokens.push(Token { kind: TokenKind::Read, span: span });
+        }
+        "while" => {
+          tokens.push(Token { kind: TokenKind::While, span: span });
+        }
+        "for" => {
+          tokens.push(Token { kind: TokenKind::For, span: span });
+        }
+        "do" => {
+          tokens.push(Token { kind: TokenKind::Do, span: span });
+        }
+        "if" => {
+          tokens.push(Token { kind: TokenKind::If, span: span });
+        }
+        "else" => {
+          tokens.push(Token { kind: TokenKind::Else, span: span });
+        }
+        "break" => {
+          tokens.push(Token { kind: TokenKind::Break, span: span });
+        }
+        "continue" => {
+          tokens.push(Token { kind: TokenKind::Continue, span: span });
+        }
+        // Else, it is a identifier
+        _ => {
+          // change &str -> String
+          let token = Token { kind: TokenKind::Ident(string_token.to_string()), span: span };
+          tokens.push(token);
+        }
+      }
+    }
+
+    // Simple symbols
+    // No need further clarification
+    '+' => {
+      i += 1;
+      // '+=', '++' or just '+'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::PlusAssign, span: span });
+        i += 1;
+      } else if i < bytes.len() && bytes[i] as char == '+' {
+        tokens.push(Token { kind: TokenKind::Increment, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Plus, span: span });
+      }
+    }
+    '-' => {
+      i += 1;
+      // '-=', '--' or just '-'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::SubtractAssign, span: span });
+        i += 1;
+      } else if i < bytes.len() && bytes[i] as char == '-' {
+        tokens.push(Token { kind: TokenKind::Decrement, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Subtract, span: span });
+      }
+    }
+    '*' => {
+      i += 1;
+      // '*=', or just '*'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::MultiplyAssign, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Multiply, span: span });
+      }
+    }
+    '/' => {
+      i += 1;
+      // '/=', or just '/'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::DivideAssign, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Divide, span: span });
+      }
+    }
+    '(' => {
+      tokens.push(Token { kind: TokenKind::LeftParen, span: span });
+      i += 1;
+    }
+    ')' => {
+      tokens.push(Token { kind: TokenKind::RightParen, span: span });
+      i += 1;
+    }
+    '{' => {
+      tokens.push(Token { kind: TokenKind::LeftCurly, span: span });
+      i += 1;
+    }
+    '}' => {
+      tokens.push(Token { kind: TokenKind::RightCurly, span: span });
+      i += 1;
+    }
+    '[' => {
+      tokens.push(Token { kind: TokenKind::LeftBracket, span: span });
+      i += 1;
+    }
+    ']' => {
+      tokens.push(Token { kind: TokenKind::RightBracket, span: span });
+      i += 1;
+    }
+    ',' => {
+      tokens.push(Token { kind: TokenKind::Comma, span: span });
+      i += 1;
+    }
+    ';' => {
+      tokens.push(Token { kind: TokenKind::Semicolon, span: span });
+      i += 1;
+    }
+    '%' => {
+      i += 1;
+      // '%=', or just '%'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::ModulusAssign, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Modulus, span: span });
+      }
+    }
+
+    // Special symbols
+    // Need to check the next character behind the current character
+    '>' => {
+      i += 1;
+      // If this is the end of the string
+      if i >= bytes.len(){
+        tokens.push(Token { kind: TokenKind::Greater, span: span });      
+      } else {
+        // Insert token based on what is next character
+        let curr = bytes[i] as char;
+        match curr {
+          '=' => {
+            tokens.push(Token { kind: TokenKind::GreaterEqual, span: span });
+            i += 1;
+          }
+          '>' => {
+            tokens.push(Token { kind: TokenKind::ShiftRight, span: span });
+            i += 1;
+          }
+          _ => {
+            tokens.push(Token { kind: TokenKind::Greater, span: span });
+          }
+        }
+      }
+    }
+    '<' => {
+      i += 1;
+      // If this is the end of the string
+      if i >= bytes.len(){
+        tokens.push(Token { kind: TokenKind::Less, span: span });
+      } else {
+        // Insert token based on what is next character
+        let curr = bytes[i] as char;
+        match curr {
+          '=' => {
+            tokens.push(Token { kind: TokenKind::LessEqual, span: span });
+            i += 1;
+          }
+          '<' => {
+            tokens.push(Token { kind: TokenKind::ShiftLeft, span: span });
+            i += 1;
+          }
+          _ => {
+            tokens.push(Token { kind: TokenKind::Less, span: span });
+          }
+        }
+      }
+    }
+    '=' => {
+      i += 1;
+      // If this is the end of the string
+      if i >= bytes.len(){
+        tokens.push(Token { kind: TokenKind::Assign, span: span });
+      } else {
+        // Insert token based on what is next character
+        let curr = bytes[i] as char;
+        match curr {
+          '=' => {
+            tokens.push(Token { kind: TokenKind::Equality, span: span });
+            i += 1;
+          }
+          _ => {
+            tokens.push(Token { kind: TokenKind::Assign, span: span });
+          }
+        }
+      }
+    }
+    '!' => {
+      i += 1;
+      // '!=' or just '!'
+      if i < bytes.len() && bytes[i] as char == '=' {
+        tokens.push(Token { kind: TokenKind::NotEqual, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::Not, span: span });
+      }
+    }
+    '&' => {
+      i += 1;
+      // '&&' or just '&'
+      if i < bytes.len() && bytes[i] as char == '&' {
+        tokens.push(Token { kind: TokenKind::And, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::BitAnd, span: span });
+      }
+    }
+    '|' => {
+      i += 1;
+      // '||' or just '|'
+      if i < bytes.len() && bytes[i] as char == '|' {
+        tokens.push(Token { kind: TokenKind::Or, span: span });
+        i += 1;
+      } else {
+        tokens.push(Token { kind: TokenKind::BitOr, span: span });
+      }
+    }
+    '^' => {
+      tokens.push(Token { kind: TokenKind::BitXor, span: span });
+      i += 1;
+    }
+    '~' => {
+      tokens.push(Token { kind: TokenKind::BitNot, span: span });
+      i += 1;
+    }
+
+    // Comment
+    // We will ignore all characters following '#' until newline (\n)
+    '#' => {
+      i += 1;
+      while i < bytes.len(){
+        let curr = bytes[i] as char;
+        if curr != '\n'{
+          i += 1;
+        } else {
+          i += 1;
+          break;
+        }
+      }
+    }
+
+    // Space or newline
+    ' ' | '\n' => {
+      i += 1;
+    }
+    
+    // If other characters encountered
+    // Return error message
+    _ => {
+      let symbol = &code[i..char_end(code, i)];
+      return Err(Diagnostic::new(span, format!("Unrecognized symbol '{}'", symbol)));
+    }
+
+    }
+  }
+
+  // The end of file token points just past the last character
+  while scanned < bytes.len() {
+    if bytes[scanned] == b'\n' {
+      line += 1;
+      line_start = scanned + 1;
+    }
+    scanned += 1;
+  }
+  let span = Span { offset: bytes.len(), line: line, column: bytes.len() - line_start + 1 };
+  tokens.push(Token { kind: TokenKind::End, span: span });
+  return Ok(tokens);
+}
@@ -0,0 +1,293 @@
+// The Teh Tarik compiler and IR interpreter as a library.
+//
+// The stages of the pipeline can be used one at a time:
+//   lex           source code -> tokens
+//   parse         tokens -> syntax tree
+//   compile_to_ir syntax tree -> IR code, after checking its types
+//   parse_ir      IR code -> bytecode
+//   run           bytecode -> exit code of main, reading from an Input
+//                 and writing to an Output (run_with_options picks
+//                 the RunOptions, such as the OverflowMode)
+// or compile() goes straight from source code to IR code.
+
+pub mod ast;
+pub mod diagnostic;
+pub mod lexer;
+pub mod parser;
+mod codegen;
+mod interpreter;
+mod symbol_table;
+pub mod typecheck;
+
+use ast::Program;
+use diagnostic::Diagnostic;
+use lexer::Token;
+
+pub use interpreter::{ExecutionLimit, Frame, IRError, IRProgram, Input, Output, OverflowMode, RunOptions, StdinInput, StdoutOutput};
+
+// The error returned by compile(), telling which stage of the compiler failed.
+#[derive(Debug, Clone)]
+pub enum CompileError {
+    Lexer(Diagnostic),
+    Parser(Vec<Diagnostic>),
+    Semantic(Diagnostic),
+}
+
+impl CompileError {
+    pub fn diagnostics(&self) -> Vec<&Diagnostic> {
+        match self {
+        CompileError::Lexer(error) => vec![error],
+        CompileError::Parser(errors) => errors.iter().collect(),
+        CompileError::Semantic(error) => vec![error],
+        }
+    }
+}
+
+// Splits the source code into tokens. The last token is always TokenKind::End.
+pub fn lex(code: &str) -> Result<Vec<Token>, Diagnostic> {
+    return lexer::lex(code);
+}
+
+// Parses the tokens into a syntax tree, reporting every syntax error found.
+pub fn parse(tokens: &Vec<Token>) -> Result<Program, Vec<Diagnostic>> {
+    let mut index: usize = 0;
+    return parser::parse_program(tokens, &mut index);
+}
+
+// Checks the program and generates its IR code.
+// The type checker fills in the IR names of the variables on a copy of the
+// tree, so the program given is not changed.
+pub fn compile_to_ir(program: &Program) -> Result<String, Diagnostic> {
+    let mut program = program.clone();
+    if let Err(error) = typecheck::check_program(&mut program) {
+        return Err(Diagnostic::from(error));
+    }
+    return codegen::lower_program(&program);
+}
+
+// Runs the lexer, the parser and the code generator on the source code.
+pub fn compile(code: &str) -> Result<String, CompileError> {
+    let tokens = match lex(code) {
+    Ok(tokens) => tokens,
+    Err(error) => return Err(CompileError::Lexer(error)),
+    };
+
+    let program = match parse(&tokens) {
+    Ok(program) => program,
+    Err(errors) => return Err(CompileError::Parser(errors)),
+    };
+
+    match compile_to_ir(&program) {
+    Ok(ir) => return Ok(ir),
+    Err(error) => return Err(CompileError::Semantic(error)),
+    }
+}
+
+// Lexes and parses IR code into bytecode that can be run.
+pub fn parse_ir(code: &str) -> Result<IRProgram, IRError> {
+    return interpreter::load_ir(code);
+}
+
+// Runs the main function of the program and returns its exit code.
+// The numbers read by the program come from input, and the numbers it prints go to output.
+pub fn run<I: Input, O: Output>(program: &IRProgram, input: &mut I, output: &mut O) -> Result<i32, IRError> {
+    return interpreter::run_ir(program, input, output, &RunOptions::default());
+}
+
+// Same as run(), with settings such as what to do on integer overflow.
+pub fn run_with_options<I: Input, O: Output>(program: &IRProgram, input: &mut I, output: &mut O, options: &RunOptions) -> Result<i32, IRError> {
+    return interpreter::run_ir(program, input, output, options);
+}
+
+// Parses and runs IR code for the tests, returning the result of main and the numbers it printed.
+#[cfg(test)]
+fn execute_ir(ir: &str, input: Vec<i32>, options: &RunOptions) -> (Result<i32, IRError>, Vec<String>) {
+    let program = parse_ir(ir).unwrap();
+    let mut input = input;
+    let mut output: Vec<String> = vec![];
+    let result = run_with_options(&program, &mut input, &mut output, options);
+    return (result, output);
+}
+
+#[cfg(test)]
+mod api_tests {
+    use crate::*;
+
+    #[test]
+    fn compile_and_run() {
+        let ir = compile("func add(int a, int b) { return a + b; }\nfunc main() { int c = add(2, 3); return c; }").unwrap();
+        let program = parse_ir(&ir).unwrap();
+        let mut output: Vec<String> = vec![];
+        assert!(run(&program, &mut vec![], &mut output).unwrap() == 5);
+        assert!(output.is_empty());
+
+        assert!(matches!(compile("func main() { int a = $; }"), Err(CompileError::Lexer(_))));
+        assert!(matches!(compile("func main() { a = ; b = ; }"), Err(CompileError::Parser(errors)) if errors.len() == 2));
+        assert!(matches!(compile("func main() { a = 1; }"), Err(CompileError::Semantic(_))));
+
+        let error = parse_ir("%func main\n%mov a, 1\n%endfunc\n").err().unwrap();
+        assert!(error.line == Some(2));
+        assert!(parse_ir("%func f\n%endfunc\n").err().unwrap().line.is_none());
+        let error = parse_ir("%func main\n%int a\n:begin\n%jmp :begin\n%branch_if a, :nowhere\n%endfunc\n").err().unwrap();
+        assert!(error.line == Some(5));
+        let error = parse_ir("%func main\n%int a\n%call a, f(1)\n%endfunc\n").err().unwrap();
+        assert!(error.line == Some(3) && error.message == "Error. Undeclared function 'f'");
+        let error = parse_ir("%func f(%int a)\n%ret a\n%endfunc\n%func main\n%call f()\n%endfunc\n").err().unwrap();
+        assert!(error.line == Some(5));
+    }
+
+    #[test]
+    fn malformed_input() {
+        // every prefix of a valid program is either valid or an error, but never a panic
+        let ir = compile("func f(int a, int b) { return a * b; }\nfunc main() { int [3] c; int t = c[1]; if t < 2 { c[0] = f(t, 2); } print(c[0]); }").unwrap();
+        for end in 0..ir.len() {
+            let _ = parse_ir(&ir[..end]);
+        }
+        assert!(parse_ir("%func main\n%int[] c, 3\n%int t\n%mov t, [c +").is_err());
+
+        assert!(parse(&vec![]).is_err());
+        // characters outside of ASCII are reported whole
+        assert!(lex("func main() { int a€ = 1; }").unwrap_err().message == "Detect invalid identifier a€");
+        assert!(lex("func main() { int 1é = 1; }").unwrap_err().message == "Detect invalid identifier 1é");
+        assert!(lex("func main() { int b = 1 € 2; }").unwrap_err().message == "Unrecognized symbol '€'");
+        assert!(lex("func main() { int aê = 1; }").is_err());
+        assert!(lex("# a comment with ünïcode\nfunc main() { }").is_ok());
+        let mut tokens = lex("func main() { }").unwrap();
+        tokens.pop();
+        assert!(parse(&tokens).is_err());
+    }
+
+    #[test]
+    fn program_io() {
+        let ir = compile("func main() {\n  int a;\n  int b;\n  read(a);\n  read(b);\n  print(a * b);\n  print(a - b);\n}").unwrap();
+        let program = parse_ir(&ir).unwrap();
+        let mut output: Vec<String> = vec![];
+        run(&program, &mut vec![6, 7], &mut output).unwrap();
+        assert!(output == vec!["42", "-1"]);
+
+        // running out of input is a runtime error
+        let mut output: Vec<String> = vec![];
+        assert!(run(&program, &mut vec![6], &mut output).is_err());
+        assert!(output.is_empty());
+
+        // numbers can be read straight into an array
+        let ir = compile("func main() {\n  int [2] a;\n  int i = 1;\n  read(a[i]);\n  print(a[1]);\n}").unwrap();
+        let (result, output) = execute_ir(&ir, vec![3], &RunOptions::default());
+        assert!(result.is_ok() && output == vec!["3"]);
+    }
+
+    #[test]
+    fn source_line_mappings() {
+        let code = "func div(int a, int b) {
+            return a / b;
+        }
+        func main() {
+            int [2] arr;
+            int i = 0;
+            do {
+                i += 1;
+            } while div(4, 2 - i) > 1;
+            arr[i] = 1;
+        }";
+        let ir = compile(code).unwrap();
+        assert!(ir.contains("%func div(%int a, %int b)\n%line 2\n"));
+        assert!(ir.contains(":_loop1_continue\n%line 9\n"));
+
+        // the error is in div, called from the condition of the do while loop
+        let error = execute_ir(&ir, vec![], &RunOptions::default()).0.unwrap_err();
+        assert!(error.function == Some(String::from("div")));
+        assert!(error.source_line == Some(2));
+        let line = error.line.unwrap();
+        assert!(ir.lines().nth(line - 1).unwrap().starts_with("%div "));
+        assert!(error.to_string().starts_with(&format!("Error at line {} in function 'div' (source line 2). ", line)));
+
+        // IR written by hand has no mappings, but still has line numbers
+        let error = execute_ir("%func main\n%int[] a, 2\n%mov [a + 2], 1\n%endfunc\n", vec![], &RunOptions::default()).0.unwrap_err();
+        assert!(error.line == Some(3));
+        assert!(error.function == Some(String::from("main")));
+        assert!(error.source_line.is_none());
+        assert!(error.to_string() == "Error at line 3 in function 'main'. Runtime Error: Array out of bounds. Value 2. Array Length 2");
+        assert!(error.backtrace_string(10) == "main");
+    }
+
+    #[test]
+    fn backtraces() {
+        let code = "func fib(int n) {
+            if n < 2 {
+                int [1] a;
+                return a[n];
+            }
+            return fib(n - 1) + fib(n - 2);
+        }
+        func main() {
+            print(fib(3));
+        }";
+        let error = execute_ir(&compile(code).unwrap(), vec![], &RunOptions::default()).0.unwrap_err();
+        assert!(error.backtrace_string(10) == "main -> fib(3) -> fib(2) -> fib(1)");
+        assert!(error.backtrace.iter().all(|frame| frame.arguments.len() <= 1));
+        assert!(error.backtrace[3].instr_pointer != error.backtrace[2].instr_pointer);
+
+        // deep recursion only shows main and the innermost calls
+        let code = "func f(int n) { if n == 0 { return 1 / n; } return f(n - 1); } func main() { return f(30); }";
+        let error = execute_ir(&compile(code).unwrap(), vec![], &RunOptions::default()).0.unwrap_err();
+        assert!(error.backtrace.len() == 32);
+        assert!(error.backtrace_string(4) == "main -> ... 28 more ... -> f(2) -> f(1) -> f(0)");
+    }
+
+    #[test]
+    fn call_depth() {
+        // recursion far deeper than the stack of the interpreter could hold
+        let code = "func sum(int n) { if n == 0 { return 0; } return n + sum(n - 1); } func main() { print(sum(60000)); }";
+        let ir = compile(code).unwrap();
+        let (result, output) = execute_ir(&ir, vec![], &RunOptions::default());
+        assert!(result.is_ok() && output == vec!["1800030000"]);
+
+        // going past the maximum call depth is a clean error
+        let options = RunOptions { max_call_depth: 100, ..RunOptions::default() };
+        let error = execute_ir(&ir, vec![], &options).0.unwrap_err();
+        assert!(error.message.starts_with("Runtime Error. Stack overflow."));
+        assert!(error.backtrace.len() == 100);
+        assert!(error.function == Some(String::from("sum")));
+        assert!(error.backtrace_string(3) == "main -> ... 97 more ... -> sum(59903) -> sum(59902)");
+
+        // and the depth of the recursion is counted in frames, main included
+        let options = RunOptions { max_call_depth: 1, ..RunOptions::default() };
+        let error = execute_ir(&ir, vec![], &options).0.unwrap_err();
+        assert!(error.backtrace_string(10) == "main");
+    }
+
+    #[test]
+    fn execution_limits() {
+        let ir = compile("func main() { int i = 0; while i < 1 { print(i); i = 0; } }").unwrap();
+
+        // the instruction budget stops the loop at the same point every time
+        let options = RunOptions { max_instructions: Some(500), ..RunOptions::default() };
+        let (result, first) = execute_ir(&ir, vec![], &options);
+        let error = result.unwrap_err();
+        assert!(error.limit == Some(ExecutionLimit::Instructions));
+        assert!(error.instructions == 500);
+        assert!(error.message == "Execution limit exceeded. The program was stopped after 500 instructions.");
+        let (result, second) = execute_ir(&ir, vec![], &options);
+        assert!(result.is_err());
+        assert!(!first.is_empty() && first == second);
+
+        let options = RunOptions { timeout: Some(std::time::Duration::from_millis(20)), ..RunOptions::default() };
+        let error = execute_ir(&ir, vec![], &options).0.unwrap_err();
+        assert!(error.limit == Some(ExecutionLimit::Timeout));
+        assert!(error.instructions > 0);
+
+        // programs that finish within the budget are not affected, and other errors are not limits
+        let ir = compile("func main() { int [1] a; print(a[2]); }").unwrap();
+        let options = RunOptions { max_instructions: Some(500), ..RunOptions::default() };
+        let error = execute_ir(&ir, vec![], &options).0.unwrap_err();
+        assert!(error.limit.is_none());
+        assert!(error.instructions > 0 && error.instructions < 500);
+
+        // labels and source line mappings are not counted, so they do not change the budget
+        let unmapped: String = ir.lines().filter(|line| !line.starts_with("%line")).map(|line| format!("{line}\n")).collect();
+        let other = execute_ir(&unmapped, vec![], &options).0.unwrap_err();
+        assert!(other.instructions == error.instructions);
+        let error = execute_ir("%func main\n:begin\n%line 1\n%jmp :begin\n%endfunc\n", vec![], &options).0.unwrap_err();
+        assert!(error.instructions == 500 && error.line == Some(4));
+    }
+}
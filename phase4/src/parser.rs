@@ -0,0 +1,1134 @@
+use crate::ast::{BinaryOp, Expr, ExprKind, Function, LogicalOp, Param, Program, Stmt, StmtKind, UnaryOp};
+use crate::diagnostic::Diagnostic;
+use crate::lexer::{Token, TokenKind};
+
+// The maximum number of parser errors reported for a single file.
+pub const MAX_ERRORS: usize = 10;
+
+// parse programs with multiple functions
+// loop over everything, collecting the parsed functions.
+// When a syntax error is found, the parser records it, skips ahead to a point
+// where it can resume (see synchronize), and keeps going, so all the errors
+// in the file are reported together. A program with any errors is never returned.
+pub fn parse_program(tokens: &Vec<Token>, index: &mut usize) -> Result<Program, Vec<Diagnostic>> {
+    if !matches!(tokens.last(), Some(Token { kind: TokenKind::End, .. })) {
+      return Err(vec![Diagnostic::without_span(String::from("The tokens must end with TokenKind::End"))]);
+    }
+
+    let mut functions: Vec<Function> = vec![];
+    let mut errors: Vec<Diagnostic> = vec![];
+    while !at_end(tokens, *index) {
+      match parse_function(tokens, index, &mut errors) {
+      Ok(function) => {
+        functions.push(function);
+      }
+      Err(e) => {
+        report(&mut errors, e);
+        // Skip the rest of the broken function
+        while !matches!(tokens[*index].kind, TokenKind::Func | TokenKind::End) {
+          *index += 1;
+        }
+      }
+      }
+    }
+
+    if errors.len() > MAX_ERRORS {
+      errors.truncate(MAX_ERRORS);
+      errors.push(Diagnostic::without_span(format!("Too many errors. Only the first {MAX_ERRORS} errors are shown.")));
+    }
+
+    if !errors.is_empty() {
+      return Err(errors);
+    }
+    return Ok(Program { functions: functions });
+}
+
+// Records a parser error. Errors at the same token as the previous error are
+// usually caused by the previous one, so they are not reported twice.
+fn report(errors: &mut Vec<Diagnostic>, error: Diagnostic) {
+    if let Some(last) = errors.last() {
+      if last.span == error.span {
+        return;
+      }
+    }
+    errors.push(error);
+}
+
+// After an error inside a statement, skip ahead to the next ';' (consumed),
+// or up to the next '}' or 'func', where the parser can pick up again.
+// A block opened by the broken statement, such as the body of a while loop
+// with an invalid condition, is skipped as a whole.
+fn synchronize(tokens: &Vec<Token>, index: &mut usize) {
+    let mut depth = 0;
+    loop {
+      match tokens[*index].kind {
+      TokenKind::Semicolon if depth == 0 => {
+        *index += 1;
+        return;
+      }
+      TokenKind::LeftCurly => {
+        depth += 1;
+        *index += 1;
+      }
+      TokenKind::RightCurly => {
+        if depth == 0 {
+          return;
+        }
+        depth -= 1;
+        *index += 1;
+        if depth == 0 {
+          return;
+        }
+      }
+      TokenKind::Func | TokenKind::End => {
+        return;
+      }
+      _ => {
+        *index += 1;
+      }
+      }
+    }
+}
+
+fn at_end(tokens: &Vec<Token>, index: usize) -> bool {
+  match tokens[index].kind {
+  TokenKind::End => { true }
+  _ => { false }
+  }
+}
+
+// parse function such as:
+// func main(int a, int b) {
+//    # ... statements here...
+//    # ...
+// }
+// a loop is done to handle statements.
+
+fn parse_function(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Function, Diagnostic> {
+    
+    match tokens[*index].kind {
+    TokenKind::Func => { *index += 1; }
+    _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("functions must begin with func"))); }
+    }
+
+    let name: String;
+    let span = tokens[*index].span;
+    let mut params: Vec<Param> = vec![];
+
+    match &tokens[*index].kind {
+    TokenKind::Ident(ident) => {
+        *index += 1;
+        name = ident.clone();
+    }
+    _  => { return Err(Diagnostic::new(tokens[*index].span, String::from("functions must have a function identifier")));}
+    }
+
+
+    match tokens[*index].kind {
+    TokenKind::LeftParen => { *index += 1; }
+    _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("expected '('")));}
+    }
+
+    // If there is a declaration in the function
+    // Then we go into the loop
+    if !matches!(tokens[*index].kind, TokenKind::RightParen) {
+      // We need to first match an declaration
+      match parse_declaration(tokens, index) {
+        Ok(param) => {
+          params.push(param);
+        }
+        Err(e) => {return Err(e);}
+      }
+      // While there is Comma
+      while matches!(tokens[*index].kind, TokenKind::Comma) {
+        // We pass forward from the Comma and check one additional declaration
+        *index += 1;
+        match parse_declaration(tokens, index) {
+          Ok(param) => {
+            params.push(param);
+          }
+          Err(e) => {return Err(e);}
+        }
+      }
+    }
+
+    match tokens[*index].kind {
+    TokenKind::RightParen => { *index += 1; }
+    _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("expected ')'")));}
+    }
+
+    let body = parse_block(tokens, index, errors)?;
+
+    return Ok(Function { name: name, params: params, body: body, span: span });
+}
+
+// parsing a statement such as:
+// int a;
+// a = a + b;
+// a = a % b;
+// print(a)
+// read(a)
+// returns epsilon if '}'
+fn parse_statement(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Stmt, Diagnostic> {
+    match tokens[*index].kind {
+    TokenKind::Int => parse_declaration_statement(tokens, index),
+    TokenKind::Ident(_) if matches!(tokens[*index + 1].kind, TokenKind::LeftParen) => parse_call_statement(tokens, index),
+    TokenKind::Ident(_) => parse_assignment_statement(tokens, index),
+    TokenKind::Return => parse_return_statement(tokens, index),
+    TokenKind::Print => parse_print_statement(tokens, index),
+    TokenKind::Read => parse_read_statement(tokens, index),
+    TokenKind::Break => parse_break_statement(tokens, index),
+    TokenKind::Continue => parse_continue_statement(tokens, index),
+    TokenKind::While => parse_while_loop(tokens, index, errors),
+    TokenKind::For => parse_for_loop(tokens, index, errors),
+    TokenKind::Do => parse_do_while_loop(tokens, index, errors),
+    TokenKind::If => parse_if_statement(tokens, index, errors),
+    _ => Err(Diagnostic::new(tokens[*index].span, String::from("invalid statement")))
+    }
+}
+
+// parses a block of statements surrounded by '{' and '}'
+// errors inside the statements are recorded, and parsing resumes at the next statement.
+fn parse_block(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Vec<Stmt>, Diagnostic> {
+    match tokens[*index].kind {
+    TokenKind::LeftCurly => { *index += 1; }
+    _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("expected '{'")));}
+    }
+
+    let mut statements: Vec<Stmt> = vec![];
+    while !matches!(tokens[*index].kind, TokenKind::RightCurly) {
+        // The block was never closed
+        if matches!(tokens[*index].kind, TokenKind::Func | TokenKind::End) {
+          return Err(Diagnostic::new(tokens[*index].span, String::from("expected '}'")));
+        }
+
+        match parse_statement(tokens, index, errors) {
+        Ok(statement) => {
+          statements.push(statement);
+        }
+        Err(e) => {
+          report(errors, e);
+          synchronize(tokens, index);
+        }
+        }
+    }
+
+    match tokens[*index].kind {
+    TokenKind::RightCurly => { *index += 1; }
+    _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("expected '}'")));}
+    }
+
+    return Ok(statements);
+}
+
+// In this phase, we do not pass in array as parameter
+fn parse_declaration(tokens: &Vec<Token>, index: &mut usize) -> Result<Param, Diagnostic> {
+
+    match tokens[*index].kind {
+    TokenKind::Int => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Declaration statements must being with 'int' keyword")));}
+    }
+
+    match &tokens[*index].kind {
+    TokenKind::Ident(ident) => {
+        let span = tokens[*index].span;
+        *index += 1;
+        return Ok(Param { name: ident.clone(), ir_name: None, span: span });
+    }
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Declarations must have an identifier")));}
+    }
+}
+
+fn parse_declaration_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+
+    match tokens[*index].kind {
+    TokenKind::Int => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Declaration statements must being with 'int' keyword")));}
+    }
+
+    // Array size if applicable
+    let mut size: Option<i32> = None;
+
+    // If it is an identifier, we can just pass forward
+    // add code that handles declarations such as int [8] arr
+    // only one size can be given, so int [4][8] arr is an error
+    if !matches!(tokens[*index].kind, TokenKind::Ident(_)) {
+      size = Some(parse_array_size(tokens, index)?);
+      if matches!(tokens[*index].kind, TokenKind::LeftBracket) {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("array size is given twice")));
+      }
+    }
+
+    let name: String;
+    match &tokens[*index].kind {
+    TokenKind::Ident(ident) => {
+        *index += 1;
+        name = ident.clone();
+    }
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Declarations must have an identifier")));}
+    }
+
+    // The array size may also follow the identifier, such as int arr[8]
+    if matches!(tokens[*index].kind, TokenKind::LeftBracket) {
+      if size.is_some() {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("array size is given twice")));
+      }
+      size = Some(parse_array_size(tokens, index)?);
+    }
+
+    // Optional initializer, such as int a = b + c
+    let mut value: Option<Expr> = None;
+    if matches!(tokens[*index].kind, TokenKind::Assign) {
+      if size.is_some() {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("arrays cannot be initialized in their declaration")));
+      }
+      *index += 1;
+      value = Some(parse_expression(tokens, index)?);
+    }
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statements must end with a semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::Declaration { name: name, ir_name: None, size: size, value: value }, span: span });
+}
+
+// parses the array size in a declaration: '[' Num ']'
+fn parse_array_size(tokens: &Vec<Token>, index: &mut usize) -> Result<i32, Diagnostic> {
+    match tokens[*index].kind {
+      TokenKind::LeftBracket => {*index += 1;}
+      _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("expected '['")));}
+    }
+
+    let size: i32;
+    match tokens[*index].kind {
+      // If it is right bracket, we are missing the array size
+      TokenKind::RightBracket => {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("missing array size")));
+      }
+      // If it is number, we need to check if it is followed by an right bracket, which is same check point
+      TokenKind::Num(num) => {
+        // Array size must not be less or equal to 0
+        if num <= 0 {
+          return Err(Diagnostic::new(tokens[*index].span, String::from("array size is less or equal to 0")));
+        }
+        size = integer_value(&tokens[*index], num)?;
+        *index += 1;
+      }
+      _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("expected ']' or number behind '['")));}
+    }
+
+    match tokens[*index].kind {
+      TokenKind::RightBracket => {*index += 1;}
+      _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("expected ']'")));}
+    }
+
+    return Ok(size);
+}
+
+// parses the destination of an assignment: either an identifier
+// or an array element such as a[i]
+fn parse_var(tokens: &Vec<Token>, index: &mut usize) -> Result<(String, Option<Expr>), Diagnostic> {
+  match &tokens[*index].kind {
+    // Start of var must be an identifier
+    TokenKind::Ident(ident) => {
+      *index += 1;
+      match tokens[*index].kind {
+        // Under Identifier, if it follows a left bracket
+        // Ident -> [ Expression ] ...
+        TokenKind::LeftBracket => {
+          *index += 1;
+
+          let expression = parse_expression(tokens, index)?;
+
+          match tokens[*index].kind {
+            TokenKind::RightBracket => {*index += 1;}
+            _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("var missing right bracket ']'"))); }
+          }
+          return Ok((ident.clone(), Some(expression)));
+        }
+        
+        // If we see other characters, that is not part of this var
+        // Identifier itself is a valid var
+        _ => {
+          return Ok((ident.clone(), None));
+        }
+      }      
+    }
+    
+    // Else, it idicates a missing identifier in var
+    _ => {
+      return Err(Diagnostic::new(tokens[*index].span, String::from("missing identifier in var")));
+    }
+
+  }
+}
+
+fn parse_assignment_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let statement = parse_assignment(tokens, index)?;
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(statement);
+}
+
+// parses an assignment without the ';' at the end,
+// which is also the form of the step of a for loop. Besides '=', these are:
+// a += b;  a -= b;  a *= b;  a /= b;  a %= b;
+// a++;  a--;
+fn parse_assignment(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+    // Get the dest (left part)
+    let (name, dest_index) = parse_var(tokens, index)?;
+
+    let op = match tokens[*index].kind {
+    TokenKind::Assign => None,
+    TokenKind::PlusAssign | TokenKind::Increment => Some(BinaryOp::Add),
+    TokenKind::SubtractAssign | TokenKind::Decrement => Some(BinaryOp::Subtract),
+    TokenKind::MultiplyAssign => Some(BinaryOp::Multiply),
+    TokenKind::DivideAssign => Some(BinaryOp::Divide),
+    TokenKind::ModulusAssign => Some(BinaryOp::Modulus),
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the '=' operator")));}
+    };
+
+    // a++ and a-- are a += 1 and a -= 1
+    let value = match tokens[*index].kind {
+    TokenKind::Increment | TokenKind::Decrement => {
+        let value_span = tokens[*index].span;
+        *index += 1;
+        Expr { kind: ExprKind::Number(1), span: value_span }
+    }
+    _ => {
+        *index += 1;
+        parse_expression(tokens, index)?
+    }
+    };
+
+    let kind = match op {
+    None => StmtKind::Assignment { name: name, ir_name: None, index: dest_index, value: value },
+    Some(op) => StmtKind::CompoundAssignment { name: name, ir_name: None, index: dest_index, op: op, value: value },
+    };
+    return Ok(Stmt { kind: kind, span: span });
+}
+
+// parses the condition of an if statement or a while loop, such as:
+// a < b && !(b == c || c > 10)
+// '||' has the lowest precedence, then '&&', then '!'.
+// '!' applies to the comparison that follows it, so !a < b means !(a < b).
+fn parse_boolean_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_and_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::Or) {
+        *index += 1;
+        let expr2 = parse_and_expression(tokens, index)?;
+        let span = expression.span;
+        expression = Expr { kind: ExprKind::Logical { op: LogicalOp::Or, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+    return Ok(expression);
+}
+
+fn parse_and_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_not_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::And) {
+        *index += 1;
+        let expr2 = parse_not_expression(tokens, index)?;
+        let span = expression.span;
+        expression = Expr { kind: ExprKind::Logical { op: LogicalOp::And, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+    return Ok(expression);
+}
+
+fn parse_not_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let span = tokens[*index].span;
+    match tokens[*index].kind {
+    TokenKind::Not => {
+        *index += 1;
+        let operand = parse_not_expression(tokens, index)?;
+        return Ok(Expr { kind: ExprKind::Unary { op: UnaryOp::Not, operand: Box::new(operand) }, span: span });
+    }
+
+    // A parenthesised condition such as (a < b || c < d).
+    // If the parentheses hold an arithmetic expression instead, as in (a + 1) < b,
+    // they are parsed again as part of a comparison.
+    TokenKind::LeftParen => {
+        let start = *index;
+        *index += 1;
+        if let Ok(expression) = parse_boolean_expression(tokens, index) {
+            if matches!(tokens[*index].kind, TokenKind::RightParen) {
+                *index += 1;
+                return Ok(expression);
+            }
+        }
+        *index = start;
+        return parse_comparison(tokens, index);
+    }
+
+    _ => {
+        return parse_comparison(tokens, index);
+    }
+    }
+}
+
+// parses a single comparison between two expressions, such as a + 1 < b
+fn parse_comparison(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let expr1 = parse_expression(tokens, index)?;
+    let op: BinaryOp;
+    match tokens[*index].kind {
+    TokenKind::Less => {
+      op = BinaryOp::Less;
+      *index += 1;
+    }
+    TokenKind::LessEqual => {
+      op = BinaryOp::LessEqual;
+      *index += 1;
+    }
+    TokenKind::Equality => {
+      op = BinaryOp::Equality;
+      *index += 1;
+    }
+    TokenKind::NotEqual => {
+      op = BinaryOp::NotEqual;
+      *index += 1;
+    }
+    TokenKind::GreaterEqual => {
+      op = BinaryOp::GreaterEqual;
+      *index += 1;
+    }
+    TokenKind::Greater => {
+      op = BinaryOp::Greater;
+      *index += 1;
+    }
+    _ => {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("Invalid boolean expression. Must have a '<', '<=', '>', or any other comparsion operator.")));
+    }
+    }
+    let expr2 = parse_expression(tokens, index)?;
+
+    let span = expr1.span;
+    Ok(Expr { kind: ExprKind::Binary { op: op, lhs: Box::new(expr1), rhs: Box::new(expr2) }, span: span })
+}
+
+fn parse_if_statement(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Stmt, Diagnostic> {
+  let span = tokens[*index].span;
+  match tokens[*index].kind {
+    TokenKind::If => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("If statements must being with 'if' keyword")));}
+  }
+
+  let condition = parse_boolean_expression(tokens, index)?;
+  let body = parse_block(tokens, index, errors)?;
+
+  //When there is an else statement
+  let mut else_body: Vec<Stmt> = vec![];
+  if matches!(tokens[*index].kind, TokenKind::Else) {
+    *index += 1;
+    match tokens[*index].kind {
+      // else if ... is an if statement nested in the else block,
+      // so a chain of else ifs becomes a ladder of branches.
+      TokenKind::If => {
+        else_body.push(parse_if_statement(tokens, index, errors)?);
+      }
+      //We check if there is {statement*}
+      TokenKind::LeftCurly => {
+        else_body = parse_block(tokens, index, errors)?;
+
+        // The else block must be the last one
+        if matches!(tokens[*index].kind, TokenKind::Else) {
+          return Err(Diagnostic::new(tokens[*index].span, String::from("An if statement can only have one 'else' block, and it must come last")));
+        }
+      }
+      _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Expected '{' or 'if' under else statement")))}
+    }
+  }
+
+  return Ok(Stmt { kind: StmtKind::If { condition: condition, body: body, else_body: else_body }, span: span });
+}
+
+fn parse_while_loop(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+
+    match tokens[*index].kind {
+    TokenKind::While => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("While statements must being with 'while' keyword")));}
+    }
+
+    let condition = parse_boolean_expression(tokens, index)?;
+    let body = parse_block(tokens, index, errors)?;
+
+    return Ok(Stmt { kind: StmtKind::While { condition: condition, body: body }, span: span });
+}
+
+// parses a for loop such as:
+// for (int i = 0; i < n; i = i + 1) {
+//    # ... statements here...
+// }
+// The variable declared by the first part only exists inside the loop.
+fn parse_for_loop(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+
+    match tokens[*index].kind {
+    TokenKind::For => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("For statements must being with 'for' keyword")));}
+    }
+
+    match tokens[*index].kind {
+    TokenKind::LeftParen => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Expected '(' after 'for'")));}
+    }
+
+    // both forms of the first part consume the ';' after them
+    let init = match tokens[*index].kind {
+    TokenKind::Int => parse_declaration_statement(tokens, index)?,
+    _ => parse_assignment_statement(tokens, index)?,
+    };
+
+    let condition = parse_boolean_expression(tokens, index)?;
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Expected ';' after the condition of the for loop")));}
+    }
+
+    let step = parse_assignment(tokens, index)?;
+    match tokens[*index].kind {
+    TokenKind::RightParen => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Expected ')' after the step of the for loop")));}
+    }
+
+    let body = parse_block(tokens, index, errors)?;
+
+    return Ok(Stmt { kind: StmtKind::For { init: Box::new(init), condition: condition, step: Box::new(step), body: body }, span: span });
+}
+
+// parses a do while loop such as:
+// do {
+//    # ... statements here...
+// } while i < n;
+fn parse_do_while_loop(tokens: &Vec<Token>, index: &mut usize, errors: &mut Vec<Diagnostic>) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+
+    match tokens[*index].kind {
+    TokenKind::Do => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Do while statements must being with 'do' keyword")));}
+    }
+
+    let body = parse_block(tokens, index, errors)?;
+
+    match tokens[*index].kind {
+    TokenKind::While => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Expected 'while' after the body of the do while loop")));}
+    }
+
+    let condition = parse_boolean_expression(tokens, index)?;
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::DoWhile { body: body, condition: condition }, span: span });
+}
+
+fn parse_return_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+    match tokens[*index].kind {
+    TokenKind::Return => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Return statements must being with a return keyword")));}
+    }
+
+    let expression = parse_expression(tokens, index)?;
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::Return(expression), span: span });
+}
+
+// parses a function call whose return value is discarded, such as:
+// log_value(x);
+fn parse_call_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+    let (name, args) = match parse_term(tokens, index)? {
+    Expr { kind: ExprKind::Call { name, args }, .. } => (name, args),
+    expression => return Err(Diagnostic::new(expression.span, String::from("expected a function call"))),
+    };
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::Call { name: name, args: args }, span: span });
+}
+
+fn parse_print_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+    match tokens[*index].kind {
+    TokenKind::Print=> {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Print statements must being with a return keyword")));}
+    }
+
+    let expression = parse_expression(tokens, index)?;
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::Print(expression), span: span });
+}
+
+fn parse_read_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+    let span = tokens[*index].span;
+    match tokens[*index].kind {
+    TokenKind::Read => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Read statements must being with a 'read' keyword")));}
+    }
+
+    let expression = parse_expression(tokens, index)?;
+
+    match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' semicolon")));}
+    }
+
+    return Ok(Stmt { kind: StmtKind::Read(expression), span: span });
+}
+
+fn parse_break_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+  let span = tokens[*index].span;
+  match tokens[*index].kind {
+    TokenKind::Break=> {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Break statements must begin with a break keyword")));}
+  }
+
+  match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' operator")));}
+  }
+
+  return Ok(Stmt { kind: StmtKind::Break, span: span });
+}
+
+fn parse_continue_statement(tokens: &Vec<Token>, index: &mut usize) -> Result<Stmt, Diagnostic> {
+  let span = tokens[*index].span;
+  match tokens[*index].kind {
+    TokenKind::Continue=> {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Continue statements must begin with a continue keyword")));}
+  }
+
+  match tokens[*index].kind {
+    TokenKind::Semicolon => {*index += 1;}
+    _ => {return Err(Diagnostic::new(tokens[*index].span, String::from("Statement is missing the ';' operator")));}
+  }
+
+  return Ok(Stmt { kind: StmtKind::Continue, span: span });
+}
+
+// parsing complex expressions such as: "a + b - (c * d) / (f + g - 8);
+// The binary operators follow the precedence levels of C, from the loosest:
+// '|', '^', '&', '<<' and '>>', '+' and '-', then '*', '/' and '%'.
+// Comparisons only appear in conditions, and bind looser than all of them.
+fn parse_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_xor_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::BitOr) {
+        *index += 1;
+        let expr2 = parse_xor_expression(tokens, index)?;
+        let span = expression.span;
+        expression = Expr { kind: ExprKind::Binary { op: BinaryOp::BitOr, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+    return Ok(expression);
+}
+
+fn parse_xor_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_bitand_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::BitXor) {
+        *index += 1;
+        let expr2 = parse_bitand_expression(tokens, index)?;
+        let span = expression.span;
+        expression = Expr { kind: ExprKind::Binary { op: BinaryOp::BitXor, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+    return Ok(expression);
+}
+
+fn parse_bitand_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_shift_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::BitAnd) {
+        *index += 1;
+        let expr2 = parse_shift_expression(tokens, index)?;
+        let span = expression.span;
+        expression = Expr { kind: ExprKind::Binary { op: BinaryOp::BitAnd, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+    return Ok(expression);
+}
+
+fn parse_shift_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_add_expression(tokens, index)?;
+    loop {
+       let op = match tokens[*index].kind {
+       TokenKind::ShiftLeft => BinaryOp::ShiftLeft,
+       TokenKind::ShiftRight => BinaryOp::ShiftRight,
+       _ => {
+           break;
+       }
+       };
+
+       *index += 1;
+       let expr2 = parse_add_expression(tokens, index)?;
+       let span = expression.span;
+       expression = Expr { kind: ExprKind::Binary { op: op, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+
+    return Ok(expression);
+}
+
+fn parse_add_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_multiply_expression(tokens, index)?;
+    loop {
+       let op = match tokens[*index].kind {
+       TokenKind::Plus => BinaryOp::Add,
+       TokenKind::Subtract => BinaryOp::Subtract,
+       _ => { 
+           break;
+       }
+       };
+
+       *index += 1;
+       let expr2 = parse_multiply_expression(tokens, index)?;
+       let span = expression.span;
+       expression = Expr { kind: ExprKind::Binary { op: op, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+
+    return Ok(expression);
+}
+
+fn parse_multiply_expression(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let mut expression = parse_term(tokens, index)?;
+    loop {
+       let op = match tokens[*index].kind {
+       TokenKind::Multiply => BinaryOp::Multiply,
+       TokenKind::Divide => BinaryOp::Divide,
+       TokenKind::Modulus => BinaryOp::Modulus,
+       _ => {
+           break;
+       }
+       };
+
+       *index += 1;
+       let expr2 = parse_term(tokens, index)?;
+       let span = expression.span;
+       expression = Expr { kind: ExprKind::Binary { op: op, lhs: Box::new(expression), rhs: Box::new(expr2) }, span: span };
+    }
+
+    return Ok(expression);
+}
+
+// Checks that the value of an integer literal fits in an i32
+fn integer_value(token: &Token, number: i64) -> Result<i32, Diagnostic> {
+    match i32::try_from(number) {
+    Ok(number) => Ok(number),
+    Err(_) => Err(Diagnostic::new(token.span, format!("integer literal {number} is out of range"))),
+    }
+}
+
+// a term is either a Number or an Identifier.
+fn parse_term(tokens: &Vec<Token>, index: &mut usize) -> Result<Expr, Diagnostic> {
+    let span = tokens[*index].span;
+    match &tokens[*index].kind {
+
+    // If just a number, return immediately
+    TokenKind::Num(number) => {
+        let number = integer_value(&tokens[*index], *number)?;
+        *index += 1;
+        return Ok(Expr { kind: ExprKind::Number(number), span: span });
+    }
+
+    // Unary minus. A minus sign in front of a number is part of the number,
+    // which is how -2147483648 can be written.
+    TokenKind::Subtract => {
+        *index += 1;
+        if let TokenKind::Num(number) = tokens[*index].kind {
+            let number = integer_value(&tokens[*index], -number)?;
+            *index += 1;
+            return Ok(Expr { kind: ExprKind::Number(number), span: span });
+        }
+        let operand = parse_term(tokens, index)?;
+        return Ok(Expr { kind: ExprKind::Unary { op: UnaryOp::Negate, operand: Box::new(operand) }, span: span });
+    }
+
+    // Bitwise complement
+    TokenKind::BitNot => {
+        *index += 1;
+        let operand = parse_term(tokens, index)?;
+        return Ok(Expr { kind: ExprKind::Unary { op: UnaryOp::BitNot, operand: Box::new(operand) }, span: span });
+    }
+
+    // If it is identifier
+    TokenKind::Ident(identifier) => {
+      *index += 1;
+      match tokens[*index].kind {
+        // Under Identifier, if it follows a left bracket
+        // Ident -> [ Expression ] ...
+        TokenKind::LeftBracket => {
+          *index += 1;
+          let inner_expr = parse_expression(tokens, index)?;
+
+          match tokens[*index].kind {
+            TokenKind::RightBracket => {*index += 1;}
+            _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("term missing right bracket ']'"))); }
+          }
+
+          return Ok(Expr { kind: ExprKind::Index { name: identifier.clone(), ir_name: None, index: Box::new(inner_expr) }, span: span });
+        }
+        // Under Identifier, if it follows a left Parenthesis
+        // Identifier (Expression(, Expression)*)
+        TokenKind::LeftParen => {
+          // We have start with a left parenthesis and a expression
+          *index += 1;
+          // Collect function arguments.
+          let mut args = Vec::new();
+
+          // A function without parameters is called with empty parenthesis
+          if !matches!(tokens[*index].kind, TokenKind::RightParen) {
+            // Push the collected argument
+            args.push(parse_expression(tokens, index)?);
+
+            // If there are more expressions between parenthesis
+            // It must start with a comma
+            // Else, it will be checked and throw an error in the next check point
+            while matches!(tokens[*index].kind, TokenKind::Comma) {
+              *index += 1;
+              // Then we can parse another expression
+              args.push(parse_expression(tokens, index)?);
+            }
+          }
+
+          match tokens[*index].kind {
+            TokenKind::RightParen => {*index += 1;}
+            _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("term missing right parenthesis ')'"))); }
+          }
+
+          return Ok(Expr { kind: ExprKind::Call { name: identifier.clone(), args: args }, span: span });
+        }
+        // If we see other characters, that is not part of this term
+        // Identifier itself is a valid term
+        _ => {
+          return Ok(Expr { kind: ExprKind::Ident { name: identifier.clone(), ir_name: None }, span: span });
+        }
+      }      
+    }
+
+    // ( Expression )
+    TokenKind::LeftParen => {
+        *index += 1;
+        let expression = parse_expression(tokens, index)?;
+
+        match tokens[*index].kind {
+        TokenKind::RightParen => {*index += 1;}
+        _ => { return Err(Diagnostic::new(tokens[*index].span, String::from("missing right parenthesis ')'"))); }
+        }
+        return Ok(expression);
+    }
+    
+    // Missing term
+    _ => {
+        return Err(Diagnostic::new(tokens[*index].span, String::from("missing expression term.")));
+    }
+
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use crate::ast::{BinaryOp, Expr, ExprKind, LogicalOp, StmtKind, UnaryOp};
+    use crate::diagnostic::Span;
+    use crate::lexer::{lex, Token, TokenKind};
+    use crate::parser::{parse_program, MAX_ERRORS};
+
+    #[test]
+    fn parse_ast() {
+        let tokens = lex("func main() { int a; a = 1 + 2 * 3; print(a); }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        assert!(program.functions.len() == 1);
+        let function = &program.functions[0];
+        assert!(function.name == "main");
+        assert!(function.params.is_empty());
+        assert!(function.body.len() == 3);
+        assert!(matches!(&function.body[0].kind, StmtKind::Declaration { name, size: None, value: None, .. } if name == "a"));
+        match &function.body[1].kind {
+        StmtKind::Assignment { name, index: None, value, .. } => {
+            assert!(name == "a");
+            match &value.kind {
+            ExprKind::Binary { op: BinaryOp::Add, lhs, rhs } => {
+                assert!(matches!(lhs.kind, ExprKind::Number(1)));
+                assert!(matches!(rhs.kind, ExprKind::Binary { op: BinaryOp::Multiply, .. }));
+            }
+            _ => panic!("expected an addition"),
+            }
+        }
+        _ => panic!("expected an assignment"),
+        }
+        assert!(matches!(&function.body[2].kind, StmtKind::Print(Expr { kind: ExprKind::Ident { .. }, .. })));
+    }
+
+    #[test]
+    fn token_spans() {
+        let tokens = lex("func main() {\n  int a;\n}").unwrap();
+        assert!(tokens[0].span == Span { offset: 0, line: 1, column: 1 });
+        assert!(tokens[1].span == Span { offset: 5, line: 1, column: 6 });
+        assert!(tokens[5].span == Span { offset: 16, line: 2, column: 3 });
+        assert!(tokens[8].span == Span { offset: 23, line: 3, column: 1 });
+        assert!(matches!(tokens[9].kind, TokenKind::End));
+
+        let code = "func main() {\n  int a;\n  a = 1 +;\n}";
+        let tokens = lex(code).unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        let error = &errors[0];
+        assert!(error.render("test.tt", code) == "test.tt:3:10: missing expression term.\n  a = 1 +;\n         ^");
+    }
+
+    #[test]
+    fn parse_error_recovery() {
+        let code = "func main() {\n  int a;\n  a = 1 +;\n  while a < { a = 2; }\n  a = ;\n  print(a);\n}\nfunc f( {\n}\n";
+        let tokens = lex(code).unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        assert!(errors.len() == 4);
+        assert!(errors[0].span.unwrap().line == 3);
+        assert!(errors[1].span.unwrap().line == 4);
+        assert!(errors[2].span.unwrap().line == 5);
+        assert!(errors[3].span.unwrap().line == 8);
+
+        // the number of reported errors is capped
+        let code = "func main() {\n".to_string() + &"  a = ;\n".repeat(MAX_ERRORS + 5) + "}\n";
+        let tokens = lex(&code).unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        assert!(errors.len() == MAX_ERRORS + 1);
+        assert!(errors[MAX_ERRORS].span.is_none());
+    }
+
+    #[test]
+    fn negative_numbers() {
+        let tokens = lex("func main() { int a = -5; a = 3 - -a * 2; a = -2147483648; }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        let body = &program.functions[0].body;
+        assert!(matches!(&body[0].kind, StmtKind::Declaration { value: Some(Expr { kind: ExprKind::Number(-5), .. }), .. }));
+        match &body[1].kind {
+        StmtKind::Assignment { value, .. } => {
+            match &value.kind {
+            ExprKind::Binary { op: BinaryOp::Subtract, rhs, .. } => {
+                match &rhs.kind {
+                ExprKind::Binary { op: BinaryOp::Multiply, lhs, .. } => {
+                    assert!(matches!(lhs.kind, ExprKind::Unary { op: UnaryOp::Negate, .. }));
+                }
+                _ => panic!("expected a multiplication"),
+                }
+            }
+            _ => panic!("expected a subtraction"),
+            }
+        }
+        _ => panic!("expected an assignment"),
+        }
+        assert!(matches!(&body[2].kind, StmtKind::Assignment { value: Expr { kind: ExprKind::Number(i32::MIN), .. }, .. }));
+
+        // only the negative literal fits in an i32
+        let tokens = lex("func main() { int a = 2147483648; }").unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        assert!(errors[0].message == "integer literal 2147483648 is out of range");
+        assert!(lex("func main() { int a = -2147483649; }").is_err());
+    }
+
+    #[test]
+    fn logical_operators() {
+        // || binds looser than &&, and parentheses can hold either a condition or an expression
+        let tokens = lex("func main() { if a < b || !(b == c) && (a + 1) < c { } }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        match &program.functions[0].body[0].kind {
+        StmtKind::If { condition, .. } => {
+            match &condition.kind {
+            ExprKind::Logical { op: LogicalOp::Or, lhs, rhs } => {
+                assert!(matches!(lhs.kind, ExprKind::Binary { op: BinaryOp::Less, .. }));
+                match &rhs.kind {
+                ExprKind::Logical { op: LogicalOp::And, lhs, rhs } => {
+                    assert!(matches!(lhs.kind, ExprKind::Unary { op: UnaryOp::Not, .. }));
+                    assert!(matches!(rhs.kind, ExprKind::Binary { op: BinaryOp::Less, .. }));
+                }
+                _ => panic!("expected '&&'"),
+                }
+            }
+            _ => panic!("expected '||'"),
+            }
+        }
+        _ => panic!("expected an if statement"),
+        }
+
+        let tokens = lex("func main() { while a < b && c { } }").unwrap();
+        assert!(parse_program(&tokens, &mut 0).is_err());
+    }
+
+    #[test]
+    fn else_if_chains() {
+        let tokens = lex("func main() { if a < 1 { a = 1; } else if a < 2 { a = 2; } else if a < 3 { a = 3; } else { a = 4; } }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        let mut statement = &program.functions[0].body[0];
+        for _ in 0..2 {
+            match &statement.kind {
+            StmtKind::If { body, else_body, .. } => {
+                assert!(body.len() == 1);
+                assert!(else_body.len() == 1);
+                statement = &else_body[0];
+            }
+            _ => panic!("expected an if statement"),
+            }
+        }
+        assert!(matches!(&statement.kind, StmtKind::If { else_body, .. } if matches!(else_body[0].kind, StmtKind::Assignment { .. })));
+
+        let code = "func main() {\n  if a < 1 { a = 1; } else { a = 2; } else { a = 3; }\n}";
+        let tokens = lex(code).unwrap();
+        let errors = parse_program(&tokens, &mut 0).unwrap_err();
+        assert!(errors.len() == 1);
+        assert!(errors[0].message == "An if statement can only have one 'else' block, and it must come last");
+        assert!(errors[0].span.unwrap().column == 39);
+    }
+
+    #[test]
+    fn compound_assignment_tokens() {
+        let tokens = lex("x++; x--; x+=1; x-=-1; x*=2; x/=2; x%=2; x=x+1;").unwrap();
+        let kinds: Vec<&TokenKind> = tokens.iter().map(|token: &Token| &token.kind).collect();
+        assert!(matches!(kinds[1], TokenKind::Increment));
+        assert!(matches!(kinds[4], TokenKind::Decrement));
+        assert!(matches!(kinds[7], TokenKind::PlusAssign));
+        assert!(matches!(kinds[11], TokenKind::SubtractAssign));
+        assert!(matches!(kinds[12], TokenKind::Subtract));
+        assert!(matches!(kinds[16], TokenKind::MultiplyAssign));
+        assert!(matches!(kinds[20], TokenKind::DivideAssign));
+        assert!(matches!(kinds[24], TokenKind::ModulusAssign));
+        assert!(matches!(kinds[30], TokenKind::Plus));
+
+        let tokens = lex("func main() { a[i + 1] -= 2; }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        assert!(matches!(&program.functions[0].body[0].kind, StmtKind::CompoundAssignment { index: Some(_), op: BinaryOp::Subtract, .. }));
+    }
+
+    #[test]
+    fn bitwise_precedence() {
+        // a | b ^ c & d << 1 + 2 is a | (b ^ (c & (d << (1 + 2))))
+        let tokens = lex("func main() { x = a | b ^ c & d << 1 + 2; }").unwrap();
+        let program = parse_program(&tokens, &mut 0).unwrap();
+        let mut expression = match &program.functions[0].body[0].kind {
+        StmtKind::Assignment { value, .. } => value,
+        _ => panic!("expected an assignment"),
+        };
+        for expected in [BinaryOp::BitOr, BinaryOp::BitXor, BinaryOp::BitAnd, BinaryOp::ShiftLeft, BinaryOp::Add] {
+            match &expression.kind {
+            ExprKind::Binary { op, rhs, .. } if *op == expected => expression = rhs,
+            _ => panic!("expected {:?}", expected),
+            }
+        }
+        assert!(matches!(expression.kind, ExprKind::Number(2)));
+    }
+}
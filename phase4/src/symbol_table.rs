@@ -0,0 +1,133 @@
+// The symbol table of a single function, organized as a stack of scopes.
+//
+// Scoping rules:
+// * The parameters and the top level statements of a function share one scope.
+// * Every other block ('if', 'else' and loop bodies) opens a new scope,
+//   and its declarations go away at the closing '}'.
+// * A for loop has a scope of its own around its body, for the variable
+//   declared in the first part of the loop.
+// * A name cannot be declared twice in the same scope, but a block may declare
+//   a name that shadows a variable from an enclosing scope.
+//
+// The interpreter keeps one flat set of variables per function, so every
+// declaration after the first one of a given name is renamed in the IR.
+// Source identifiers must start with a letter, so the generated names
+// (such as '_j_1') can never clash with a user variable.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Array,
+}
+
+pub struct Symbol {
+    pub name: String,
+    pub ir_name: String,
+    pub symbol_type: Type,
+}
+
+pub struct SymbolTable {
+    scopes: Vec<Vec<Symbol>>,
+    // number of times each name has been declared in the function so far
+    declarations: Vec<(String, usize)>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            scopes: vec![vec![]],
+            declarations: vec![],
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // Helper function to check if given symbol is declared in the innermost scope
+    pub fn in_current_scope(&self, name: &String) -> bool {
+        let scope = self.scopes.last().unwrap();
+        for symbol in scope {
+            if symbol.name.eq(name) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    // Declares a symbol in the innermost scope, and returns its name in the IR
+    pub fn declare(&mut self, name: &String, symbol_type: Type) -> String {
+        let mut count = 0;
+        for declaration in self.declarations.iter_mut() {
+            if declaration.0.eq(name) {
+                count = declaration.1;
+                declaration.1 += 1;
+            }
+        }
+        if count == 0 {
+            self.declarations.push((name.clone(), 1));
+        }
+
+        let ir_name = if count == 0 {
+            name.clone()
+        } else {
+            format!("_{name}_{count}")
+        };
+
+        let symbol = Symbol {
+            name: name.clone(),
+            ir_name: ir_name.clone(),
+            symbol_type: symbol_type,
+        };
+        self.scopes.last_mut().unwrap().push(symbol);
+        return ir_name;
+    }
+
+    // Finds the visible declaration of the given name, searching from the innermost scope outwards
+    pub fn lookup(&self, name: &String) -> Option<&Symbol> {
+        for scope in self.scopes.iter().rev() {
+            for symbol in scope.iter().rev() {
+                if symbol.name.eq(name) {
+                    return Some(symbol);
+                }
+            }
+        }
+        return None;
+    }
+}
+
+// The functions of a program, with their number of parameters.
+// All of the functions are declared before any function body is checked,
+// so a function can be called before its definition.
+pub struct FunctionTable {
+    functions: Vec<(String, usize)>,
+}
+
+impl FunctionTable {
+    pub fn new() -> FunctionTable {
+        FunctionTable { functions: vec![] }
+    }
+
+    // Declares a function, returns false if a function with the same name already exists
+    pub fn declare(&mut self, name: &String, parameters: usize) -> bool {
+        if self.lookup(name).is_some() {
+            return false;
+        }
+        self.functions.push((name.clone(), parameters));
+        return true;
+    }
+
+    // Returns the number of parameters of the given function
+    pub fn lookup(&self, name: &String) -> Option<usize> {
+        for function in &self.functions {
+            if function.0.eq(name) {
+                return Some(function.1);
+            }
+        }
+        return None;
+    }
+}
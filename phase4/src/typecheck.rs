@@ -0,0 +1,358 @@
+use std::fmt;
+use crate::ast::{Expr, ExprKind, Function, Program, Stmt, StmtKind};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::symbol_table::{FunctionTable, SymbolTable, Type};
+
+// The semantic pass that runs between the parser and the code generator.
+// It resolves every variable against the scopes of its function and every
+// call against the functions of the program (see symbol_table.rs), checking
+// the number of arguments of each call, and that integers and arrays are used correctly:
+// * only integers can be used in expressions, printed, read or assigned to
+// * only a variable or an array element can be read into
+// * only arrays can be indexed
+// * function parameters and return values are integers, so arrays cannot be
+//   passed to or returned from functions
+// The code generator assumes that the program has passed these checks.
+// The scoping rules are only applied here: every variable is resolved to its
+// name in the IR, which is stored in the ir_name of the node that names it.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeErrorKind {
+    UndeclaredVariable(String),
+    DuplicateParameter(String),
+    DuplicateDeclaration(String),
+    ArrayUsedAsInteger(String),
+    IntegerIndexed(String),
+    ArrayArgument { function: String, array: String },
+    ArrayReturned { function: String, array: String },
+    DuplicateFunction(String),
+    UndefinedFunction(String),
+    ArgumentCount { function: String, expected: usize, found: usize },
+    InvalidReadTarget,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeError {
+    pub kind: TypeErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+        TypeErrorKind::UndeclaredVariable(name) => write!(f, "Variable {name} called before declaration"),
+        TypeErrorKind::DuplicateParameter(name) => write!(f, "Found duplicating declaration inside function {name}"),
+        TypeErrorKind::DuplicateDeclaration(name) => write!(f, "Found duplicating declaration inside statement {name}"),
+        TypeErrorKind::ArrayUsedAsInteger(name) => write!(f, "Array {name} cannot be used as an integer"),
+        TypeErrorKind::IntegerIndexed(name) => write!(f, "Integer {name} cannot be indexed like an array"),
+        TypeErrorKind::ArrayArgument { function, array } => write!(f, "Array {array} cannot be passed to function {function}, which expects an integer"),
+        TypeErrorKind::ArrayReturned { function, array } => write!(f, "Function {function} cannot return the array {array}"),
+        TypeErrorKind::DuplicateFunction(name) => write!(f, "Found duplicating function name {name}"),
+        TypeErrorKind::UndefinedFunction(name) => write!(f, "Function {name} is not defined"),
+        TypeErrorKind::ArgumentCount { function, expected, found } => {
+            write!(f, "Function {function} expects {expected} argument(s), but {found} were given")
+        }
+        TypeErrorKind::InvalidReadTarget => write!(f, "read can only store into a variable or an array element"),
+        }
+    }
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(error: TypeError) -> Diagnostic {
+        Diagnostic::new(error.span, error.to_string())
+    }
+}
+
+fn type_error<T>(span: Span, kind: TypeErrorKind) -> Result<T, TypeError> {
+    Err(TypeError { kind: kind, span: span })
+}
+
+// The state of the type checker: the functions of the program, and the
+// name and visible variables of the function being checked.
+struct TypeContext<'a> {
+    functions: &'a FunctionTable,
+    symbol_table: SymbolTable,
+    function: String,
+}
+
+// Checks every function of the program, stopping at the first error,
+// and fills in the IR names of its variables.
+// The functions are collected first, so that calls can refer to functions defined later.
+pub fn check_program(program: &mut Program) -> Result<(), TypeError> {
+    let mut functions = FunctionTable::new();
+    for function in &program.functions {
+        if !functions.declare(&function.name, function.params.len()) {
+            return type_error(function.span, TypeErrorKind::DuplicateFunction(function.name.clone()));
+        }
+    }
+
+    for function in &mut program.functions {
+        let mut ctx = TypeContext {
+            functions: &functions,
+            symbol_table: SymbolTable::new(),
+            function: function.name.clone(),
+        };
+        check_function(function, &mut ctx)?;
+    }
+    return Ok(());
+}
+
+fn check_function(function: &mut Function, ctx: &mut TypeContext) -> Result<(), TypeError> {
+    // The parameters and the top level statements share the outermost scope
+    for param in &mut function.params {
+        if ctx.symbol_table.in_current_scope(&param.name) {
+            return type_error(param.span, TypeErrorKind::DuplicateParameter(param.name.clone()));
+        }
+        param.ir_name = Some(ctx.symbol_table.declare(&param.name, Type::Int));
+    }
+
+    for statement in &mut function.body {
+        check_statement(statement, ctx)?;
+    }
+    return Ok(());
+}
+
+fn check_block(statements: &mut Vec<Stmt>, ctx: &mut TypeContext) -> Result<(), TypeError> {
+    ctx.symbol_table.push_scope();
+    for statement in statements {
+        check_statement(statement, ctx)?;
+    }
+    ctx.symbol_table.pop_scope();
+    return Ok(());
+}
+
+fn check_statement(statement: &mut Stmt, ctx: &mut TypeContext) -> Result<(), TypeError> {
+    let span = statement.span;
+    match &mut statement.kind {
+    StmtKind::Declaration { name, ir_name, size, value } => {
+        if ctx.symbol_table.in_current_scope(name) {
+            return type_error(span, TypeErrorKind::DuplicateDeclaration(name.clone()));
+        }
+        // The initializer refers to any outer variable with the same name
+        if let Some(value) = value {
+            check_integer(value, ctx)?;
+        }
+        let symbol_type = if size.is_some() { Type::Array } else { Type::Int };
+        *ir_name = Some(ctx.symbol_table.declare(name, symbol_type));
+    }
+
+    StmtKind::Assignment { name, ir_name, index, value } | StmtKind::CompoundAssignment { name, ir_name, index, value, .. } => {
+        match index {
+        Some(index) => {
+            *ir_name = Some(expect_variable(ctx, name, Type::Array, span)?);
+            check_integer(index, ctx)?;
+        }
+        None => {
+            *ir_name = Some(expect_variable(ctx, name, Type::Int, span)?);
+        }
+        }
+        check_integer(value, ctx)?;
+    }
+
+    StmtKind::Call { name, args } => {
+        check_call(name, args, span, ctx)?;
+    }
+
+    StmtKind::Return(expr) => {
+        if check_expression(expr, ctx)? == Type::Array {
+            let kind = TypeErrorKind::ArrayReturned { function: ctx.function.clone(), array: array_name(expr) };
+            return type_error(expr.span, kind);
+        }
+    }
+
+    StmtKind::Print(expr) => {
+        check_integer(expr, ctx)?;
+    }
+
+    StmtKind::Read(expr) => {
+        match &mut expr.kind {
+        ExprKind::Ident { name, ir_name } => *ir_name = Some(expect_variable(ctx, name, Type::Int, expr.span)?),
+        ExprKind::Index { .. } => check_integer(expr, ctx)?,
+        _ => return type_error(expr.span, TypeErrorKind::InvalidReadTarget),
+        }
+    }
+
+    StmtKind::Break | StmtKind::Continue => {}
+
+    StmtKind::While { condition, body } => {
+        check_integer(condition, ctx)?;
+        check_block(body, ctx)?;
+    }
+
+    // The variable declared by init is only visible inside the loop
+    StmtKind::For { init, condition, step, body } => {
+        ctx.symbol_table.push_scope();
+        check_statement(init, ctx)?;
+        check_integer(condition, ctx)?;
+        check_block(body, ctx)?;
+        check_statement(step, ctx)?;
+        ctx.symbol_table.pop_scope();
+    }
+
+    StmtKind::DoWhile { body, condition } => {
+        check_block(body, ctx)?;
+        check_integer(condition, ctx)?;
+    }
+
+    StmtKind::If { condition, body, else_body } => {
+        check_integer(condition, ctx)?;
+        check_block(body, ctx)?;
+        check_block(else_body, ctx)?;
+    }
+    }
+    return Ok(());
+}
+
+// Checks that a variable has been declared with the expected type, and returns its IR name
+fn expect_variable(ctx: &TypeContext, name: &String, expected: Type, span: Span) -> Result<String, TypeError> {
+    match ctx.symbol_table.lookup(name) {
+    None => type_error(span, TypeErrorKind::UndeclaredVariable(name.clone())),
+    Some(found) if found.symbol_type == expected => Ok(found.ir_name.clone()),
+    Some(_) if expected == Type::Int => type_error(span, TypeErrorKind::ArrayUsedAsInteger(name.clone())),
+    Some(_) => type_error(span, TypeErrorKind::IntegerIndexed(name.clone())),
+    }
+}
+
+// Checks an expression whose value must be an integer
+fn check_integer(expr: &mut Expr, ctx: &mut TypeContext) -> Result<(), TypeError> {
+    if check_expression(expr, ctx)? == Type::Array {
+        return type_error(expr.span, TypeErrorKind::ArrayUsedAsInteger(array_name(expr)));
+    }
+    return Ok(());
+}
+
+// Returns the type of an expression.
+// Only a plain identifier can be an array, everything else is an integer.
+fn check_expression(expr: &mut Expr, ctx: &mut TypeContext) -> Result<Type, TypeError> {
+    match &mut expr.kind {
+    ExprKind::Number(_) => Ok(Type::Int),
+
+    ExprKind::Ident { name, ir_name } => {
+        match ctx.symbol_table.lookup(name) {
+        Some(found) => {
+            *ir_name = Some(found.ir_name.clone());
+            Ok(found.symbol_type)
+        }
+        None => type_error(expr.span, TypeErrorKind::UndeclaredVariable(name.clone())),
+        }
+    }
+
+    ExprKind::Index { name, ir_name, index } => {
+        *ir_name = Some(expect_variable(ctx, name, Type::Array, expr.span)?);
+        check_integer(index, ctx)?;
+        Ok(Type::Int)
+    }
+
+    ExprKind::Call { name, args } => {
+        check_call(name, args, expr.span, ctx)?;
+        Ok(Type::Int)
+    }
+
+    ExprKind::Unary { operand, .. } => {
+        check_integer(operand, ctx)?;
+        Ok(Type::Int)
+    }
+
+    ExprKind::Binary { lhs, rhs, .. } | ExprKind::Logical { lhs, rhs, .. } => {
+        check_integer(lhs, ctx)?;
+        check_integer(rhs, ctx)?;
+        Ok(Type::Int)
+    }
+    }
+}
+
+// Checks that the function exists, and is given the right number of integer arguments
+fn check_call(name: &String, args: &mut Vec<Expr>, span: Span, ctx: &mut TypeContext) -> Result<(), TypeError> {
+    let expected = match ctx.functions.lookup(name) {
+    Some(parameters) => parameters,
+    None => return type_error(span, TypeErrorKind::UndefinedFunction(name.clone())),
+    };
+    if args.len() != expected {
+        let kind = TypeErrorKind::ArgumentCount { function: name.clone(), expected: expected, found: args.len() };
+        return type_error(span, kind);
+    }
+    for arg in args {
+        if check_expression(arg, ctx)? == Type::Array {
+            let kind = TypeErrorKind::ArrayArgument { function: name.clone(), array: array_name(arg) };
+            return type_error(arg.span, kind);
+        }
+    }
+    return Ok(());
+}
+
+fn array_name(expr: &Expr) -> String {
+    match &expr.kind {
+    ExprKind::Ident { name, .. } => name.clone(),
+    _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod typecheck_tests {
+    use crate::lexer::lex;
+    use crate::parser::parse_program;
+    use crate::typecheck::*;
+
+    fn check(code: &str) -> Result<(), TypeError> {
+        let tokens = lex(code).unwrap();
+        let mut program = parse_program(&tokens, &mut 0).unwrap();
+        check_program(&mut program)
+    }
+
+    #[test]
+    fn array_and_integer_types() {
+        assert!(check("func f(int a) { return a; } func main() { int [4] a; int i = 1; a[i] = f(a[0] + i); print(a[a[1]]); }").is_ok());
+
+        let error = check("func main() { int [4] a; int b = a + 1; }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayUsedAsInteger(String::from("a")));
+        assert!(error.span.column == 34);
+        let error = check("func main() { int [4] a; print(a); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayUsedAsInteger(String::from("a")));
+        let error = check("func main() { int [4] a; int i; while a < 1 { i = 1; } }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayUsedAsInteger(String::from("a")));
+        let error = check("func main() { int b; int c = b[0]; }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::IntegerIndexed(String::from("b")));
+        let error = check("func main() { int b; if 1 < 2 { print(c); } }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::UndeclaredVariable(String::from("c")));
+    }
+
+    #[test]
+    fn read_targets() {
+        assert!(check("func main() { int [4] a; int i; read(i); read(a[i + 1]); }").is_ok());
+
+        let error = check("func main() { read(5); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::InvalidReadTarget);
+        assert!(error.span.column == 20);
+        let error = check("func main() { int a; read(a + 1); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::InvalidReadTarget);
+        let error = check("func main() { int [4] a; read(a); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayUsedAsInteger(String::from("a")));
+    }
+
+    #[test]
+    fn function_types() {
+        let error = check("func f(int a) { return a; } func main() { int [4] a; int b = f(a); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayArgument { function: String::from("f"), array: String::from("a") });
+        assert!(error.to_string() == "Array a cannot be passed to function f, which expects an integer");
+
+        let error = check("func f(int b) { int [4] a; return a; } func main() { int b = f(1); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArrayReturned { function: String::from("f"), array: String::from("a") });
+        assert!(error.to_string() == "Function f cannot return the array a");
+    }
+
+    #[test]
+    fn function_calls() {
+        // functions can be called before they are defined
+        assert!(check("func main() { print(f(1) + 1); } func f(int a) { return g(a, a); } func g(int a, int b) { return a * b; }").is_ok());
+
+        let code = "func main() {\n  int a = 1;\n  if f(a) < 1 { print(a); }\n}\nfunc f(int a, int b) { return a; }";
+        let error = check(code).unwrap_err();
+        assert!(error.kind == TypeErrorKind::ArgumentCount { function: String::from("f"), expected: 2, found: 1 });
+        assert!(error.span.line == 3 && error.span.column == 6);
+
+        let error = check("func main() { int a; a = 1 + h(a); }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::UndefinedFunction(String::from("h")));
+        let error = check("func main() { } func f(int a) { return a; } func f(int b) { return b; }").unwrap_err();
+        assert!(error.kind == TypeErrorKind::DuplicateFunction(String::from("f")));
+    }
+}